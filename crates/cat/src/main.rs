@@ -1,124 +1,233 @@
-use clap::{command, Arg, ArgAction};
+use clap::{Arg, ArgAction, command};
 use std::env;
 use std::error::Error;
 use std::fs;
-use std::io::{self, Stdin, Write};
-
-/// A struct to store the parsed flags from the command line
-#[derive(Debug)]
-struct OutputFlags {
-    numbers: bool,
-    squeeze_blank: bool,
-    number_noblank: bool,
-    show_ends: bool,
-    show_tabs: bool,
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use utils::pipeline::{Eol, OutputFlags, SqueezeMode, generate_output, unwrap_lines};
+use utils::reverse::{ReversedOutput, Separator, open_seekable};
+
+/// Counters of the line endings found in one input
+#[derive(Debug, Default, PartialEq)]
+struct EolCounts {
+    lf: usize,
+    crlf: usize,
+    cr: usize,
 }
 
-/// Takes a strins that may have  one or
-/// more EOL characters and separaes the lines to return a vector
-/// of single line strigns.
-fn unwrap_lines(data: &str) -> Vec<String> {
-    data.split('\n').map(String::from).collect::<Vec<String>>()
-}
+impl EolCounts {
+    /// Adds the line endings found in the data to the counters,
+    /// a CR is only counted alone if it is not followed by LF
+    fn add(&mut self, data: &str) {
+        let mut chars = data.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    self.crlf += 1;
+                }
+                '\r' => self.cr += 1,
+                '\n' => self.lf += 1,
+                _ => {}
+            }
+        }
+    }
 
-/// Get a slice of string and replaces all tabs with ^I character
-fn replace_tabs(data: &str) -> String {
-    data.replace("\t", "^I")
+    /// Returns the name of the line ending style, mixed if
+    /// more than one style was found
+    fn style(&self) -> &'static str {
+        let found = [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&n| n > 0)
+            .count();
+        match found {
+            0 => "none",
+            1 if self.lf > 0 => "lf",
+            1 if self.crlf > 0 => "crlf",
+            1 => "cr",
+            _ => "mixed",
+        }
+    }
 }
 
-/// Pre processes the string, does the replacements required and
-/// splits all lines into a vector of Strigs
-fn preprocess_line(data: &str, flags: &OutputFlags) -> Vec<String> {
-    if flags.show_tabs {
-        unwrap_lines(&replace_tabs(data))
-    } else {
-        unwrap_lines(data)
-    }
+/// Prints the line ending summary of one input to stderr
+fn report_eol(fname: &str, counts: &EolCounts) {
+    eprintln!(
+        "{}: lf={} crlf={} cr={} ({})",
+        fname,
+        counts.lf,
+        counts.crlf,
+        counts.cr,
+        counts.style()
+    );
 }
 
-/// Appends a line number at the beggining of every line,
-/// if the ignore_blanks flag is set, does not add a number to
-/// empty lines
-fn append_line_number(
-    data: &[String],
-    ignore_blanks: bool,
-    starting_number: usize,
-) -> (Vec<String>, usize) {
-    // Calculate the right alignment of the number column
-    let nc = data.len().to_string().len();
+/// Reads a list of file names separated by the separator character
+/// from a file, or from stdin if the file name is -. Empty names are skipped.
+fn read_file_list(list_name: &str, separator: u8) -> io::Result<Vec<Vec<u8>>> {
+    let data = if list_name == "-" {
+        let mut data = vec![];
+        io::stdin().lock().read_to_end(&mut data)?;
+        data
+    } else {
+        fs::read(list_name)?
+    };
 
-    let mut line_number = starting_number;
-    let output = data
-        .iter()
-        .map(|line| {
-            let mut s = String::new();
-            if (!ignore_blanks) || (!line.is_empty()) {
-                line_number += 1;
-                s = format!("{:>nc$} {}", line_number, line);
-            }
-            s
-        })
-        .collect();
+    Ok(data
+        .split(|&c| c == separator)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_vec())
+        .collect())
+}
 
-    (output, line_number)
+/// Appends the file names listed in a file to the input files, or
+/// records the error if the list cannot be read. The names that are
+/// not valid UTF-8 are recorded as errors and the rest are kept.
+fn add_file_list(
+    list_name: &str,
+    separator: u8,
+    input_files: &mut Vec<String>,
+    errors: &mut Vec<(String, io::Error)>,
+) {
+    let names = match read_file_list(list_name, separator) {
+        Ok(names) => names,
+        Err(e) => {
+            errors.push((format!("Error reading file list {list_name}"), e));
+            return;
+        }
+    };
+    for name in names {
+        match String::from_utf8(name) {
+            Ok(name) => input_files.push(name),
+            Err(e) => errors.push((
+                format!(
+                    "Invalid file name {} in file list {list_name}",
+                    e.as_bytes().escape_ascii()
+                ),
+                io::Error::new(io::ErrorKind::InvalidData, e.utf8_error()),
+            )),
+        }
+    }
 }
 
-fn append_eol_character(data: &[String]) -> Vec<String> {
-    data.iter().map(|x| format!("{}$", x)).collect()
+/// Destination of the concatenated output, stdout or a temporary
+/// file that replaces the output file once all the inputs were read
+struct OutputSink {
+    stdout: io::BufWriter<io::Stdout>,
+    file: Option<OutputFile>,
+    tee: bool,
 }
 
-fn remove_consecutive_empty_lines(data: &[String], prev_emptylines: usize) -> (Vec<String>, usize) {
-    let mut empty_line_counter = prev_emptylines;
+/// Temporary file created next to the final output file
+struct OutputFile {
+    handle: io::BufWriter<fs::File>,
+    tmp_path: PathBuf,
+    path: PathBuf,
+}
 
-    let output: Vec<String> = data
-        .iter()
-        .filter(|line| {
-            if line.is_empty() {
-                empty_line_counter += 1;
-            } else {
-                empty_line_counter = 0;
-            }
-            empty_line_counter < 2
+impl OutputSink {
+    fn new(output: Option<&str>, append: bool, tee: bool) -> io::Result<OutputSink> {
+        let file = match output {
+            Some(path) => Some(OutputFile::create(Path::new(path), append)?),
+            None => None,
+        };
+        Ok(OutputSink {
+            stdout: io::BufWriter::new(io::stdout()),
+            file,
+            tee,
         })
-        .map(|x| x.to_owned())
-        .collect();
+    }
 
-    (output, empty_line_counter)
-}
+    /// Writes the lines to the output file and, if there is no output
+    /// file or tee is set, to stdout
+    fn print_output(&mut self, data: &[Vec<u8>]) -> io::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            print_output(&mut file.handle, data)?;
+        }
+        if self.file.is_none() || self.tee {
+            print_output(&mut self.stdout, data)?;
+        }
+        Ok(())
+    }
 
-fn print_output(data: &[String]) {
-    let stdout = io::stdout();
-    let mut handle = io::BufWriter::new(stdout);
-    for lines in data.iter() {
-        // println!("{data}");
-        // TODO: handle the Err
-        let _ = writeln!(handle, "{lines}");
+    /// Flushes stdout and, if commit is set, moves the temporary file
+    /// over the output file, otherwise the temporary file is removed
+    fn finish(mut self, commit: bool) -> io::Result<()> {
+        let flushed = self.stdout.flush();
+        let committed = match self.file {
+            Some(file) => file.finish(commit && flushed.is_ok()),
+            None => Ok(()),
+        };
+        flushed.and(committed)
     }
 }
 
-fn generate_output(
-    data: &[String],
-    output_flags: &OutputFlags,
-    empty_line_counter: usize,
-    last_line_number: usize,
-) -> (Vec<String>, usize, usize) {
-    // Split lines with mulitple end of line into separate vector entries
-    let mut output = data.to_owned();
-    let mut empty_line_counter = empty_line_counter;
-    let mut last_line_number = last_line_number;
-    if output_flags.squeeze_blank {
-        (output, empty_line_counter) = remove_consecutive_empty_lines(&output, empty_line_counter);
-    }
-    if output_flags.number_noblank {
-        (output, last_line_number) = append_line_number(&output, true, last_line_number);
-    } else if output_flags.numbers {
-        (output, last_line_number) = append_line_number(&output, false, last_line_number);
+impl OutputFile {
+    /// Creates a temporary file in the directory of the output file,
+    /// when appending it starts with a copy of the current contents
+    fn create(path: &Path, append: bool) -> io::Result<OutputFile> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid output file"))?;
+        let tmp_path = dir.join(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        let prepared = match fs::metadata(path) {
+            Ok(metadata) => fs::set_permissions(&tmp_path, metadata.permissions()).and_then(|_| {
+                if append {
+                    io::copy(&mut fs::File::open(path)?, &mut file).map(|_| ())
+                } else {
+                    Ok(())
+                }
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = prepared {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        Ok(OutputFile {
+            handle: io::BufWriter::new(file),
+            tmp_path,
+            path: path.to_owned(),
+        })
     }
-    if output_flags.show_ends {
-        output = append_eol_character(&output);
+
+    fn finish(mut self, commit: bool) -> io::Result<()> {
+        let result = if commit {
+            self.handle
+                .flush()
+                .and_then(|_| self.handle.get_ref().sync_all())
+                .and_then(|_| fs::rename(&self.tmp_path, &self.path))
+        } else {
+            Ok(())
+        };
+        if !commit || result.is_err() {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+        result
     }
+}
 
-    (output, empty_line_counter, last_line_number)
+fn print_output(handle: &mut impl Write, data: &[Vec<u8>]) -> io::Result<()> {
+    for line in data.iter() {
+        handle.write_all(line)?;
+        handle.write_all(b"\n")?;
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -157,8 +266,19 @@ fn main() -> Result<(), Box<dyn Error>> {
             Arg::new("squeeze-blank")
                 .short('s')
                 .long("squeeze-blank")
-                .action(ArgAction::SetTrue)
-                .help("Remove consecutive empty lines"),
+                .value_name("MODE")
+                .value_parser(["strict", "whitespace"])
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("strict")
+                .help("Remove consecutive empty lines, whitespace also squeezes lines with only blanks"),
+        )
+        .arg(
+            Arg::new("max-blank")
+                .long("max-blank")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("keep up to N consecutive blank lines when squeezing, implies -s"),
         )
         .arg(
             Arg::new("show-tabs")
@@ -167,29 +287,144 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .action(ArgAction::SetTrue)
                 .help("display TAB character as ^I"),
         )
+        .arg(
+            Arg::new("eol")
+                .long("eol")
+                .value_name("STYLE")
+                .value_parser(["lf", "crlf", "keep"])
+                .default_value("keep")
+                .help("rewrite line endings of the output as LF, CRLF or keep them"),
+        )
+        .arg(
+            Arg::new("report-eol")
+                .long("report-eol")
+                .action(ArgAction::SetTrue)
+                .help("print to stderr a summary of the line endings found in each FILE"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("OUTPUT")
+                .help("write to OUTPUT instead of stdout, only replaced if every FILE was read"),
+        )
+        .arg(
+            Arg::new("append")
+                .long("append")
+                .action(ArgAction::SetTrue)
+                .requires("output")
+                .help("append to OUTPUT instead of overwriting it"),
+        )
+        .arg(
+            Arg::new("tee")
+                .long("tee")
+                .action(ArgAction::SetTrue)
+                .requires("output")
+                .help("also copy the output to stdout"),
+        )
+        .arg(
+            Arg::new("files0-from")
+                .long("files0-from")
+                .value_name("F")
+                .action(ArgAction::Append)
+                .help("read input from the files named in F, separated by NUL; - reads the names from stdin"),
+        )
+        .arg(
+            Arg::new("files-from")
+                .long("files-from")
+                .value_name("F")
+                .action(ArgAction::Append)
+                .help("read input from the files named in F, one per line; - reads the names from stdin"),
+        )
+        .arg(
+            Arg::new("reverse")
+                .short('R')
+                .long("reverse")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("report-eol")
+                .help("print the lines of each FILE in reverse order, last line first"),
+        )
+        .arg(
+            Arg::new("number-original")
+                .long("number-original")
+                .action(ArgAction::SetTrue)
+                .requires("reverse")
+                .help("with -R number the lines with their position in the FILE"),
+        )
         .get_matches();
 
-    let mut input_files = matches
-        .get_many::<String>("FILE")
-        .unwrap_or_default()
-        .map(|v| v.as_str())
-        .collect::<Vec<_>>();
+    let mut contents: Vec<Vec<u8>> = vec![];
+    let mut errors = vec![];
 
-    // If no files were defined, force the use of stdin
-    if input_files.is_empty() {
-        input_files.push("-");
+    // The inputs keep the order of the command line, the file lists and
+    // the arguments starting with @ are replaced by the names listed in them
+    let mut operands = vec![];
+    for arg in ["FILE", "files0-from", "files-from"] {
+        if let (Some(values), Some(indices)) =
+            (matches.get_many::<String>(arg), matches.indices_of(arg))
+        {
+            operands.extend(indices.zip(values).map(|(idx, value)| (idx, arg, value)));
+        }
+    }
+    operands.sort_by_key(|&(idx, _, _)| idx);
+    let mut input_files = vec![];
+    for (_, arg, value) in operands {
+        match (arg, value.strip_prefix('@')) {
+            ("files0-from", _) => add_file_list(value, b'\0', &mut input_files, &mut errors),
+            ("files-from", _) => add_file_list(value, b'\n', &mut input_files, &mut errors),
+            (_, Some(list)) if !list.is_empty() => {
+                add_file_list(list, b'\n', &mut input_files, &mut errors)
+            }
+            _ => input_files.push(value.to_owned()),
+        }
     }
 
-    // Iterate over the valid input files and load the contents into memory
-    let mut contents: Vec<String> = vec![];
-    let mut errors = vec![];
+    // If no files were defined, force the use of stdin
+    let has_file_lists = matches.contains_id("files0-from") || matches.contains_id("files-from");
+    if input_files.is_empty() && !has_file_lists {
+        input_files.push(String::from("-"));
+    }
 
     let mut output_flags: OutputFlags = OutputFlags {
         numbers: matches.get_flag("numbers"),
-        squeeze_blank: matches.get_flag("squeeze-blank"),
+        squeeze_blank: matches.contains_id("squeeze-blank") || matches.contains_id("max-blank"),
+        squeeze_mode: match matches
+            .get_one::<String>("squeeze-blank")
+            .map(|v| v.as_str())
+        {
+            Some("whitespace") => SqueezeMode::Whitespace,
+            _ => SqueezeMode::Strict,
+        },
+        max_blank: matches.get_one::<usize>("max-blank").copied().unwrap_or(1),
         number_noblank: matches.get_flag("number-noblank"),
         show_ends: matches.get_flag("show-ends"),
         show_tabs: matches.get_flag("show-tabs"),
+        number_descending: matches.get_flag("number-original"),
+        number_width: 0,
+        eol: match matches.get_one::<String>("eol").map(|v| v.as_str()) {
+            Some("lf") => Eol::Lf,
+            Some("crlf") => Eol::Crlf,
+            _ => Eol::Keep,
+        },
+    };
+    let report = matches.get_flag("report-eol");
+    let reverse = matches.get_flag("reverse");
+
+    let output_file = matches.get_one::<String>("output").map(|v| v.as_str());
+    let mut sink = match OutputSink::new(
+        output_file,
+        matches.get_flag("append"),
+        matches.get_flag("tee"),
+    ) {
+        Ok(sink) => sink,
+        Err(e) => {
+            return Err(format!(
+                "Error creating output file {}: {}",
+                output_file.unwrap_or("-"),
+                e
+            )
+            .into());
+        }
     };
     if matches.get_flag("show-all") {
         output_flags.show_ends = true;
@@ -200,12 +435,49 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut empty_line_counter = 0;
     //tmp A counter of the last printed line number
     let mut last_line_number = 0;
-    for fname in input_files.iter() {
-        if fname != &"-" {
+    'inputs: for fname in input_files.iter() {
+        if reverse {
+            let mut output = match open_seekable(fname).and_then(|reader| {
+                ReversedOutput::new(
+                    reader,
+                    Separator::Literal(b"\n".to_vec()),
+                    &output_flags,
+                    empty_line_counter,
+                    last_line_number,
+                )
+            }) {
+                Ok(output) => output,
+                Err(e) => {
+                    errors.push((format!("Error reading file {fname}"), e));
+                    continue;
+                }
+            };
+            for record in output.by_ref() {
+                match record {
+                    // The separator is always a LF, as the one added by print_output
+                    Ok((contents, _)) => {
+                        if let Err(e) = sink.print_output(&contents) {
+                            errors.push((String::from("Error writing output"), e));
+                            break 'inputs;
+                        }
+                    }
+                    Err(e) => {
+                        errors.push((format!("Error reading file {fname}"), e));
+                        break;
+                    }
+                }
+            }
+            (empty_line_counter, last_line_number) = output.counters();
+        } else if fname != "-" {
             match fs::read_to_string(fname) {
                 Ok(data) => {
+                    if report {
+                        let mut counts = EolCounts::default();
+                        counts.add(&data);
+                        report_eol(fname, &counts);
+                    }
                     let output = generate_output(
-                        &preprocess_line(&data, &output_flags),
+                        &unwrap_lines(data.as_bytes()),
                         &output_flags,
                         empty_line_counter,
                         last_line_number,
@@ -213,7 +485,10 @@ fn main() -> Result<(), Box<dyn Error>> {
                     contents = output.0;
                     empty_line_counter = output.1;
                     last_line_number = output.2;
-                    print_output(&contents);
+                    if let Err(e) = sink.print_output(&contents) {
+                        errors.push((String::from("Error writing output"), e));
+                        break 'inputs;
+                    }
                 }
                 Err(e) => {
                     let err_msg = format!("Error reading file {fname}");
@@ -221,10 +496,23 @@ fn main() -> Result<(), Box<dyn Error>> {
                 }
             }
         } else {
-            let stdin: Stdin = io::stdin();
-            for line in stdin.lines() {
+            // Read line by line keeping the CR, only the LF is removed
+            let mut stdin = io::stdin().lock();
+            let mut counts = EolCounts::default();
+            let mut line = String::new();
+            loop {
+                match stdin.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        errors.push((String::from("Error reading stdin"), e));
+                        break;
+                    }
+                }
+                counts.add(&line);
+                let data = line.strip_suffix('\n').unwrap_or(&line);
                 let output = generate_output(
-                    &preprocess_line(&line.unwrap(), &output_flags),
+                    &unwrap_lines(data.as_bytes()),
                     &output_flags,
                     empty_line_counter,
                     last_line_number,
@@ -232,11 +520,26 @@ fn main() -> Result<(), Box<dyn Error>> {
                 contents = output.0;
                 empty_line_counter = output.1;
                 last_line_number = output.2;
-                print_output(&contents);
+                if let Err(e) = sink.print_output(&contents) {
+                    errors.push((String::from("Error writing output"), e));
+                    break 'inputs;
+                }
+                line.clear();
+            }
+            if report {
+                report_eol(fname, &counts);
             }
         }
     }
 
+    // The output file is only replaced if all the inputs were concatenated
+    let commit = errors.is_empty();
+    if let Err(e) = sink.finish(commit) {
+        errors.push((String::from("Error writing output"), e));
+    } else if !commit && let Some(output_file) = output_file {
+        eprintln!("{output_file} was not modified");
+    }
+
     if errors.is_empty() {
         Ok(())
     } else {
@@ -252,143 +555,27 @@ fn main() -> Result<(), Box<dyn Error>> {
 mod cat_tests {
     use super::*;
 
-    fn generate_test_string(n_lines: usize) -> String {
-        let mut output: String = "".to_owned();
-        match n_lines {
-            0 => output,
-            1 => String::from("Line 1"),
-            2.. => {
-                for i in 1..n_lines {
-                    output.push_str(&format!("Line {}\n", i));
-                }
-                output.push_str(&format!("Line {}", n_lines - 1));
-                output
-            }
-        }
-    }
-
-    fn generate_test_vector(n_lines: usize) -> Vec<String> {
-        let mut lines = vec![];
-        for i in 1..=n_lines {
-            lines.push(format!("Line {}", i).to_owned());
-        }
-        lines
-    }
-
-    #[test]
-    fn replace_tabs_check() {
-        // do nothing on empty strings
-        let empty = "";
-        assert!(empty.is_empty());
-        let empty = replace_tabs(empty);
-        assert!(empty.is_empty());
-
-        // Do nothing to strings with no tabs
-        let no_tabs = "One string without tabs";
-        let replaced = replace_tabs(no_tabs);
-        assert_eq!(no_tabs, replaced);
-
-        // Replace tabs wit ^I
-        let with_tabs = "One\tstring\twithout\ttabs";
-        let no_tabs = "One^Istring^Iwithout^Itabs";
-        let replaced = replace_tabs(with_tabs);
-        assert_eq!(no_tabs, replaced);
-    }
-
     #[test]
-    fn unwrap_lines_() {
-        let unwrapped = unwrap_lines("");
-        assert_eq!(1, unwrapped.len());
-
-        let n: usize = 3;
-        let orig_lines = generate_test_string(n);
-        let unwrapped = unwrap_lines(&orig_lines);
-        assert_eq!(n, unwrapped.len());
-
-        let n = 100;
-        let orig_lines = generate_test_string(n);
-        let unwrapped = unwrap_lines(&orig_lines);
-        assert_eq!(n, unwrapped.len());
-    }
-
-    #[test]
-    fn append_line_number_check_returned_size() {
-        let mut orig_lines = vec![];
-        const N: usize = 100;
-        orig_lines = generate_test_vector(N);
-
-        let mod_lines = append_line_number(&orig_lines, false, 0_usize);
-        assert_eq!(N, mod_lines.0.len());
-        assert_eq!(N, mod_lines.1);
-    }
-
-    #[test]
-    fn append_line_number_check_indexing() {
-        let mut orig_lines = vec![];
-        const N: usize = 100;
-        orig_lines = generate_test_vector(N);
-
-        let mod_lines = append_line_number(&orig_lines, false, 0_usize);
-        for (i, line) in mod_lines.0.iter().enumerate() {
-            let mut tokens = line.split_whitespace();
-            assert_eq!(i + 1, tokens.next().unwrap().parse::<usize>().unwrap())
-        }
-    }
-
-    #[test]
-    fn remove_consecutive_empty_lines_empty_input() {
-        let orig_lines = vec![];
-        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0_usize);
-        assert_eq!(0, mod_lines.0.len());
-        assert_eq!(0, mod_lines.1);
-
-        const N: usize = 100;
-
-        let mut orig_lines = vec![];
-        for _ in 0..N {
-            orig_lines.push(String::from(""));
-        }
-
-        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0_usize);
-        assert_eq!(1, mod_lines.0.len()); // one line of the repeated chunk remains
-        assert_eq!(N, mod_lines.1);
-    }
-
-    #[test]
-    fn append_eol_character_check() {
-        let orig_lines = vec![];
-        let mod_lines = append_eol_character(&orig_lines);
-        assert_eq!(0, mod_lines.len());
-
-        const N: usize = 3;
-        let orig_lines = generate_test_vector(N);
-        let mod_lines = append_eol_character(&orig_lines);
-        assert_eq!(N, mod_lines.len());
-
-        for (i, line) in mod_lines.iter().enumerate() {
-            assert_eq!(format!("Line {}$", i + 1), *line);
-        }
-    }
-
-    #[test]
-    fn remove_consecutive_empty_lines_check_count() {
-        const N: usize = 100;
-        let mut orig_lines = generate_test_vector(N);
-
-        for _ in 0..100 {
-            orig_lines.push(String::from(""));
-        }
-
-        assert_eq!(N + 100, orig_lines.len());
-        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0_usize);
-        assert_eq!(N + 1, mod_lines.0.len());
-        assert_eq!(N, mod_lines.1);
-
-        // calling the function again should not remove more lines, but should
-        // report the emoty line at the end
-
-        let mod_lines = remove_consecutive_empty_lines(&mod_lines.0, 0_usize);
-        assert_eq!(N + 1, mod_lines.0.len());
-        assert_eq!(1, mod_lines.1);
+    fn eol_counts_check() {
+        let mut counts = EolCounts::default();
+        counts.add("");
+        assert_eq!(EolCounts::default(), counts);
+        assert_eq!("none", counts.style());
+
+        counts.add("Line 1\nLine 2\n");
+        assert_eq!(2, counts.lf);
+        assert_eq!("lf", counts.style());
+
+        let mut counts = EolCounts::default();
+        counts.add("Line 1\r\nLine 2\r\n");
+        assert_eq!(2, counts.crlf);
+        assert_eq!(0, counts.lf);
+        assert_eq!("crlf", counts.style());
+
+        counts.add("Line 3\rLine 4\n");
+        assert_eq!(2, counts.crlf);
+        assert_eq!(1, counts.cr);
+        assert_eq!(1, counts.lf);
+        assert_eq!("mixed", counts.style());
     }
 }
@@ -1,5 +1,5 @@
 use assert_cmd::cargo::*;
-use assert_fs::prelude::FileWriteStr;
+use assert_fs::prelude::{FileWriteStr, PathAssert, PathChild};
 use predicates::prelude::*;
 
 mod cat_functional_tests {
@@ -157,4 +157,304 @@ mod cat_functional_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn rewrite_line_endings() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("Line 1\r\nLine 2\r\n\r\n\r\n")?;
+        file2.write_str("Line 3\nLine 4")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--eol=lf")
+            .arg("-s")
+            .arg(file1.path())
+            .arg(file2.path());
+        cmd.assert().success().stdout(predicate::str::contains(
+            "Line 1\nLine 2\n\nLine 3\nLine 4\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--eol=crlf").arg("-E").arg(file2.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 3\r$\nLine 4\r$\n"));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--eol=crlf");
+        cmd.write_stdin("Line 1\r\nLine 2\n");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 1\r\nLine 2\r\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn report_line_endings() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("Line 1\r\nLine 2\r\n")?;
+        file2.write_str("Line 3\nLine 4\r\nLine 5\r")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--report-eol").arg(file1.path()).arg(file2.path());
+        cmd.assert()
+            .success()
+            .stderr(predicate::str::contains("lf=0 crlf=2 cr=0 (crlf)"))
+            .stderr(predicate::str::contains("lf=1 crlf=1 cr=1 (mixed)"));
+
+        // The line endings are not counted when the lines are reversed
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--report-eol").arg("--reverse").arg(file1.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::is_empty())
+            .stderr(predicate::str::contains("cannot be used with"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_output_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let file1 = dir.child("first_file.txt");
+        let file2 = dir.child("second_file.txt");
+        let bundle = dir.child("bundle.txt");
+        file1.write_str("Line 1\nLine 2")?;
+        file2.write_str("Line 3\nLine 4")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg(file1.path())
+            .arg(file2.path())
+            .arg("-o")
+            .arg(bundle.path());
+        cmd.assert().success().stdout(predicate::str::is_empty());
+        bundle.assert("Line 1\nLine 2\nLine 3\nLine 4\n");
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--append")
+            .arg("--tee")
+            .arg("-o")
+            .arg(bundle.path())
+            .arg(file1.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 1\nLine 2\n"));
+        bundle.assert("Line 1\nLine 2\nLine 3\nLine 4\nLine 1\nLine 2\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_file_not_replaced_on_error() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let file1 = dir.child("first_file.txt");
+        let bundle = dir.child("bundle.txt");
+        file1.write_str("Line 1\nLine 2")?;
+        bundle.write_str("Old bundle")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg(file1.path())
+            .arg(dir.child("kk.txt").path())
+            .arg("--output")
+            .arg(bundle.path());
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("was not modified"));
+        bundle.assert("Old bundle");
+        // No temporary files are left behind
+        assert_eq!(2, std::fs::read_dir(dir.path())?.count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_file_lists() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let file1 = dir.child("first_file.txt");
+        let file2 = dir.child("second_file.txt");
+        let list0 = dir.child("list0");
+        let list = dir.child("list");
+        file1.write_str("Line 1\nLine 2")?;
+        file2.write_str("Line 3\nLine 4")?;
+        list0.write_str(&format!(
+            "{}\0{}\0",
+            file2.path().display(),
+            file1.path().display()
+        ))?;
+        list.write_str(&format!(
+            "{}\n\n{}\n",
+            file1.path().display(),
+            file2.path().display()
+        ))?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("-n").arg("--files0-from").arg(list0.path());
+        cmd.assert().success().stdout(predicate::str::contains(
+            "1 Line 3\n2 Line 4\n3 Line 1\n4 Line 2",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--files-from=-");
+        cmd.write_stdin(format!("{}\n", file2.path().display()));
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("Line 3\nLine 4\n"));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg(format!("@{}", list.path().display()));
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 1\nLine 2\nLine 3\nLine 4"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_list_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let file1 = dir.child("first_file.txt");
+        let list0 = dir.child("list0");
+        file1.write_str("Line 1\nLine 2")?;
+        list0.write_str(&format!("kk.txt\0{}", file1.path().display()))?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--files0-from")
+            .arg(list0.path())
+            .arg("--files-from")
+            .arg(dir.child("kk.list").path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("Line 1\nLine 2"))
+            .stderr(predicate::str::contains("Error reading file kk.txt"))
+            .stderr(predicate::str::contains("Error reading file list"));
+
+        // A name that is not valid UTF-8 does not discard the rest of the list
+        let list = dir.child("list");
+        let mut names = b"bad\xff.txt\n".to_vec();
+        names.extend_from_slice(format!("{}\n", file1.path().display()).as_bytes());
+        std::fs::write(list.path(), names)?;
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--files-from").arg(list.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::diff("Line 1\nLine 2\n"))
+            .stderr(predicate::str::contains("Invalid file name bad\\xff.txt"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_lists_keep_command_line_order() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let file1 = dir.child("first_file.txt");
+        let file2 = dir.child("second_file.txt");
+        let file3 = dir.child("third_file.txt");
+        let list = dir.child("list");
+        file1.write_str("Line 1")?;
+        file2.write_str("Line 2")?;
+        file3.write_str("Line 3")?;
+        list.write_str("second_file.txt\n")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.current_dir(dir.path())
+            .arg("first_file.txt")
+            .arg("--files-from=list")
+            .arg("third_file.txt")
+            .arg("--files0-from=-")
+            .arg("@list");
+        cmd.write_stdin("first_file.txt\0");
+        cmd.assert().success().stdout(predicate::str::diff(
+            "Line 1\nLine 2\nLine 3\nLine 1\nLine 2\n",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn squeeze_whitespace_lines() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("Line 1\n  \n\t\n")?;
+        file2.write_str("\n\nLine 2")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("-sE").arg(file1.path()).arg(file2.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 1$\n  $\n\t$\n$\nLine 2$"));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--squeeze-blank=whitespace")
+            .arg("-TE")
+            .arg(file1.path())
+            .arg(file2.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 1$\n  $\nLine 2$"));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--max-blank")
+            .arg("2")
+            .arg("-E")
+            .arg(file1.path())
+            .arg(file2.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Line 1$\n  $\n\t$\n$\n$\nLine 2$"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_lines() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("Line 1\nLine 2\n\n\n")?;
+        file2.write_str("Line 3\nLine 4")?;
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("-R")
+            .arg("-nsE")
+            .arg(file1.path())
+            .arg(file2.path());
+        cmd.assert().success().stdout(predicate::str::diff(
+            "1 $\n2 Line 2$\n3 Line 1$\n4 Line 4$\n5 Line 3$\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("--reverse")
+            .arg("--number-original")
+            .arg("-b")
+            .arg(file1.path())
+            .arg("-");
+        cmd.write_stdin("Line 3\nLine 4\n");
+        cmd.assert().success().stdout(predicate::str::diff(
+            "\n\n2 Line 2\n1 Line 1\n4 Line 4\n3 Line 3\n",
+        ));
+
+        // The numbers are aligned to the width of the line count
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("-Rn")
+            .write_stdin((1..=10).map(|i| format!("{i}\n")).collect::<String>());
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let output = String::from_utf8(output)?;
+        assert!(output.starts_with(" 1 10\n 2 9\n"));
+        assert!(output.ends_with("10 1\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_invalid_utf8() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = cargo_bin_cmd!("cat");
+        cmd.arg("-R").arg("-E");
+        cmd.write_stdin(&b"a\n\xff\nb\n"[..]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::eq(&b"b$\n\xff$\na$\n"[..]));
+
+        Ok(())
+    }
 } // mod
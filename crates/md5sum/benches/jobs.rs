@@ -0,0 +1,43 @@
+use assert_fs::prelude::*;
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::process::Command;
+use std::thread;
+
+/// Hashes a tree of many small files and a big one with one thread
+/// and with as many threads as CPUs
+fn hash_many_files(c: &mut Criterion) {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mut files = vec![];
+    for idx in 0..2000 {
+        let file = dir.child(format!("small_{idx:04}.bin"));
+        file.write_binary(&[idx as u8; 16 * 1024]).unwrap();
+        files.push(file.path().to_owned());
+    }
+    let big_file = dir.child("big.bin");
+    big_file
+        .write_binary(&vec![0xa5; 64 * 1024 * 1024])
+        .unwrap();
+    files.push(big_file.path().to_owned());
+
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut group = c.benchmark_group("md5sum");
+    group.sample_size(10);
+    // At least two threads, to compare them even with a single CPU
+    for jobs in [1, cpus.max(2)] {
+        group.bench_function(format!("--jobs {jobs}"), |b| {
+            b.iter(|| {
+                let output = Command::new(env!("CARGO_BIN_EXE_md5sum"))
+                    .arg("--jobs")
+                    .arg(jobs.to_string())
+                    .args(&files)
+                    .output()
+                    .unwrap();
+                assert!(output.status.success());
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, hash_many_files);
+criterion_main!(benches);
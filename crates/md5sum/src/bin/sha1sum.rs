@@ -0,0 +1,7 @@
+use md5sum::digest::Algorithm;
+use std::error::Error;
+use std::process::ExitCode;
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    md5sum::run("sha1sum", Algorithm::Sha1)
+}
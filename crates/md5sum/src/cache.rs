@@ -0,0 +1,306 @@
+//! Persistent cache of digests. The digest of a file is reused while its
+//! fingerprint, the device, inode, size and modification time, is the same.
+//!
+//! The cache is a text file with a line per file and digest type:
+//! `ALGORITHM DEV INODE SIZE MTIME DIGEST PATH`, the path is absolute and
+//! escaped as in the checksum lines. Concurrent runs are serialized with a
+//! lock on `FILE.lock` while the cache is read and written.
+
+use crate::digest::Algorithm;
+use crate::escape;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const HEADER: &str = "# md5sum cache 1";
+
+/// Metadata that changes when the contents of a file change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fingerprint {
+    dev: u64,
+    ino: u64,
+    pub size: u64,
+    /// Modification time in nanoseconds
+    mtime: i128,
+}
+
+impl Fingerprint {
+    pub fn of(path: &Path) -> io::Result<Fingerprint> {
+        let metadata = fs::metadata(path)?;
+        Ok(Fingerprint {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            size: metadata.size(),
+            mtime: metadata.mtime() as i128 * 1_000_000_000 + metadata.mtime_nsec() as i128,
+        })
+    }
+}
+
+type Entries = HashMap<(Algorithm, OsString), (Fingerprint, String)>;
+
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    entries: Entries,
+    /// Digests computed in this run, written back by save
+    updated: Mutex<Entries>,
+    /// Percentage of the cached files hashed again to verify the cache
+    verify_percent: u64,
+    random: RandomState,
+    /// Cached digests found wrong when the files were hashed again
+    mismatches: AtomicUsize,
+}
+
+impl Cache {
+    /// Loads the cache, a missing file is an empty cache
+    pub fn open(path: &Path, verify_percent: u64) -> io::Result<Cache> {
+        let _lock = lock(path)?;
+        Ok(Cache {
+            path: path.to_owned(),
+            entries: read_entries(path)?,
+            updated: Mutex::new(HashMap::new()),
+            verify_percent,
+            random: RandomState::new(),
+            mismatches: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the cached digest of the file if the fingerprint didn't change
+    pub fn get(
+        &self,
+        file_name: &Path,
+        algo: Algorithm,
+        fingerprint: &Fingerprint,
+    ) -> Option<&str> {
+        let (cached, digest) = self
+            .entries
+            .get(&(algo, file_name.as_os_str().to_owned()))?;
+        (cached == fingerprint).then_some(digest.as_str())
+    }
+
+    /// The files are selected with the hash of their names, the seed of
+    /// RandomState changes on each run
+    pub fn is_sampled(&self, file_name: &Path) -> bool {
+        self.random.hash_one(file_name) % 100 < self.verify_percent
+    }
+
+    /// Counts a cached digest that doesn't match the file hashed again
+    pub fn add_mismatch(&self) {
+        self.mismatches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn mismatches(&self) -> usize {
+        self.mismatches.load(Ordering::Relaxed)
+    }
+
+    pub fn insert(
+        &self,
+        file_name: &Path,
+        algo: Algorithm,
+        fingerprint: Fingerprint,
+        digest: &str,
+    ) {
+        self.updated.lock().unwrap().insert(
+            (algo, file_name.as_os_str().to_owned()),
+            (fingerprint, digest.to_owned()),
+        );
+    }
+
+    /// Writes the digests computed to the cache file. The file is read again,
+    /// so the entries written by other runs since it was opened are kept.
+    /// The entries of the files removed or changed since they were hashed
+    /// would never be used again, they are dropped.
+    pub fn save(&self) -> io::Result<()> {
+        let updated = self.updated.lock().unwrap();
+        if updated.is_empty() {
+            return Ok(());
+        }
+        let _lock = lock(&self.path)?;
+        let mut entries = read_entries(&self.path)?;
+        entries.extend(
+            updated
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        let mut current = HashMap::new();
+        entries.retain(|(_, file_name), (fingerprint, _)| {
+            current
+                .entry(file_name.clone())
+                .or_insert_with(|| Fingerprint::of(Path::new(file_name)).ok())
+                .as_ref()
+                == Some(fingerprint)
+        });
+
+        let mut temp_name = self.path.as_os_str().to_owned();
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let mut output = io::BufWriter::new(File::create(&temp_name)?);
+        writeln!(output, "{}", HEADER)?;
+        for ((algo, file_name), (fingerprint, digest)) in entries.iter() {
+            write!(
+                output,
+                "{} {} {} {} {} {} ",
+                algo.name(),
+                fingerprint.dev,
+                fingerprint.ino,
+                fingerprint.size,
+                fingerprint.mtime,
+                digest
+            )?;
+            output.write_all(&escape::escape(file_name.as_bytes()).0)?;
+            output.write_all(b"\n")?;
+        }
+        output
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(&temp_name, &self.path)
+    }
+}
+
+/// Locks the cache until the returned file is dropped
+fn lock(path: &Path) -> io::Result<File> {
+    let mut lock_name = path.as_os_str().to_owned();
+    lock_name.push(".lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_name)?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Reads the entries of the cache file, the lines that can't be parsed
+/// are dropped
+fn read_entries(path: &Path) -> io::Result<Entries> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(data
+        .split(|&byte| byte == b'\n')
+        .filter_map(parse_entry)
+        .collect())
+}
+
+fn parse_entry(line: &[u8]) -> Option<((Algorithm, OsString), (Fingerprint, String))> {
+    let mut fields = line.splitn(7, |&byte| byte == b' ');
+    let mut next_str = || std::str::from_utf8(fields.next()?).ok();
+    let algo = Algorithm::from_name(next_str()?)?;
+    let fingerprint = Fingerprint {
+        dev: next_str()?.parse().ok()?,
+        ino: next_str()?.parse().ok()?,
+        size: next_str()?.parse().ok()?,
+        mtime: next_str()?.parse().ok()?,
+    };
+    let digest = next_str()?.to_owned();
+    if digest.len() != algo.bits() / 4 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let file_name = escape::unescape(fields.next()?)?;
+    Some(((algo, OsString::from_vec(file_name)), (fingerprint, digest)))
+}
+
+/// Absolute path of the file used as the key of the cache
+pub fn cache_key(file_name: &OsStr) -> io::Result<PathBuf> {
+    fs::canonicalize(file_name)
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use super::*;
+
+    #[test]
+    fn save_and_reload() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dir = temp.path();
+        let cache_file = dir.join("cache");
+        let data_file = dir.join("data\nfile");
+        fs::write(&data_file, "abc").unwrap();
+        let fingerprint = Fingerprint::of(&data_file).unwrap();
+        let digest = "900150983cd24fb0d6963f7d28e17f72";
+
+        let cache = Cache::open(&cache_file, 0).unwrap();
+        assert_eq!(None, cache.get(&data_file, Algorithm::Md5, &fingerprint));
+        cache.insert(&data_file, Algorithm::Md5, fingerprint, digest);
+        cache.save().unwrap();
+
+        let cache = Cache::open(&cache_file, 0).unwrap();
+        assert_eq!(
+            Some(digest),
+            cache.get(&data_file, Algorithm::Md5, &fingerprint)
+        );
+        assert_eq!(None, cache.get(&data_file, Algorithm::Sha1, &fingerprint));
+        assert!(!cache.is_sampled(&data_file));
+
+        // A new size or modification time is a new fingerprint
+        fs::write(&data_file, "abcd").unwrap();
+        let new_fingerprint = Fingerprint::of(&data_file).unwrap();
+        assert_ne!(fingerprint, new_fingerprint);
+        assert_eq!(
+            None,
+            cache.get(&data_file, Algorithm::Md5, &new_fingerprint)
+        );
+
+        let cache = Cache::open(&cache_file, 100).unwrap();
+        assert!(cache.is_sampled(&data_file));
+    }
+
+    #[test]
+    fn save_prunes_stale_entries() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dir = temp.path();
+        let cache_file = dir.join("cache");
+        let changed_file = dir.join("changed");
+        let removed_file = dir.join("removed");
+        fs::write(&changed_file, "abc").unwrap();
+        fs::write(&removed_file, "abc").unwrap();
+        let old_fingerprint = Fingerprint::of(&changed_file).unwrap();
+        let removed_fingerprint = Fingerprint::of(&removed_file).unwrap();
+        let digest = "900150983cd24fb0d6963f7d28e17f72";
+
+        let cache = Cache::open(&cache_file, 0).unwrap();
+        cache.insert(&changed_file, Algorithm::Md5, old_fingerprint, digest);
+        cache.insert(&changed_file, Algorithm::Sha1, old_fingerprint, digest);
+        cache.insert(&removed_file, Algorithm::Md5, removed_fingerprint, digest);
+        cache.save().unwrap();
+
+        fs::write(&changed_file, "abcd").unwrap();
+        fs::remove_file(&removed_file).unwrap();
+        let new_fingerprint = Fingerprint::of(&changed_file).unwrap();
+        let cache = Cache::open(&cache_file, 0).unwrap();
+        cache.insert(&changed_file, Algorithm::Md5, new_fingerprint, digest);
+        cache.save().unwrap();
+
+        // Only the entry of the current contents of the file is left
+        let entries = read_entries(&cache_file).unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(
+            Some(&(new_fingerprint, digest.to_owned())),
+            entries.get(&(Algorithm::Md5, changed_file.into_os_string()))
+        );
+    }
+
+    #[test]
+    fn parse_entries() {
+        let entry =
+            parse_entry(b"md5 1 2 3 -4 900150983cd24fb0d6963f7d28e17f72 /a b\\\\c").unwrap();
+        assert_eq!((Algorithm::Md5, OsString::from("/a b\\c")), entry.0);
+        assert_eq!(3, entry.1.0.size);
+        assert_eq!(-4, entry.1.0.mtime);
+
+        assert_eq!(None, parse_entry(HEADER.as_bytes()));
+        assert_eq!(None, parse_entry(b"md5 1 2 3"));
+        assert_eq!(None, parse_entry(b"md4 1 2 3 4 abc /a"));
+        assert_eq!(None, parse_entry(b"crc 1 2 3 4 xyz /a"));
+        assert_eq!(None, parse_entry(b"md5 1 2 3 4 9001 /a"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Differences between two manifests. A file is renamed when its digest
+//! is found under a new path and the old path is gone. The ranges of a
+//! file are compared as different files, named `FILE@OFFSET+LENGTH`.
+
+use crate::ChecksumRecord;
+use crate::digest::Algorithm;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::{OsStr, OsString};
+
+/// Change of a file between the old and the new manifest
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added(OsString),
+    Removed(OsString),
+    Modified(OsString),
+    /// The old and the new path of the file
+    Renamed(OsString, OsString),
+}
+
+impl Change {
+    /// Path used to sort the changes, the old one for the renamed files
+    fn path(&self) -> &OsStr {
+        match self {
+            Change::Added(path)
+            | Change::Removed(path)
+            | Change::Modified(path)
+            | Change::Renamed(path, _) => path,
+        }
+    }
+
+    /// Returns the change as a line with a status letter, as git diff --name-status
+    pub fn to_text(&self) -> String {
+        match self {
+            Change::Added(path) => format!("A  {}", path.display()),
+            Change::Removed(path) => format!("D  {}", path.display()),
+            Change::Modified(path) => format!("M  {}", path.display()),
+            Change::Renamed(old, new) => format!("R  {} -> {}", old.display(), new.display()),
+        }
+    }
+}
+
+/// The digest of a file, the hexadecimal digits are compared in lowercase
+fn digest_key(record: &ChecksumRecord) -> (Algorithm, String) {
+    (record.algo, record.hash.to_ascii_lowercase())
+}
+
+/// The path and the range of a file, the key of the records compared
+fn file_key(record: &ChecksumRecord) -> (&OsString, Option<(u64, u64)>) {
+    let range = record.range.map(|range| (range.offset, range.length));
+    (&record.file_name, range)
+}
+
+/// Compares the records of two manifests and returns the changes sorted by path
+pub fn compare(old: &[ChecksumRecord], new: &[ChecksumRecord]) -> Vec<Change> {
+    let old_files = old
+        .iter()
+        .map(|record| (file_key(record), record))
+        .collect::<BTreeMap<_, _>>();
+    let new_files = new
+        .iter()
+        .map(|record| (file_key(record), record))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut changes = vec![];
+    // The added files by digest, candidates to be the new path of the removed ones
+    let mut added = HashMap::<_, VecDeque<_>>::new();
+    for (key, record) in new_files.iter() {
+        let digest = digest_key(record);
+        match old_files.get(key) {
+            Some(old_record) if digest_key(old_record) != digest => {
+                changes.push(Change::Modified(record.display_name()));
+            }
+            Some(_) => {}
+            None => added
+                .entry(digest)
+                .or_default()
+                .push_back(record.display_name()),
+        }
+    }
+    for (key, record) in old_files.iter() {
+        if new_files.contains_key(key) {
+            continue;
+        }
+        match added
+            .get_mut(&digest_key(record))
+            .and_then(|paths| paths.pop_front())
+        {
+            Some(new_path) => changes.push(Change::Renamed(record.display_name(), new_path)),
+            None => changes.push(Change::Removed(record.display_name())),
+        }
+    }
+    for path in added.into_values().flatten() {
+        changes.push(Change::Added(path));
+    }
+
+    changes.sort_by(|a, b| a.path().cmp(b.path()));
+    changes
+}
+
+/// Returns the changes as a JSON object with a list for each kind of change
+pub fn to_json(changes: &[Change]) -> Value {
+    let mut added = vec![];
+    let mut removed = vec![];
+    let mut modified = vec![];
+    let mut renamed = vec![];
+    for change in changes {
+        match change {
+            Change::Added(path) => added.push(json!(path.to_string_lossy())),
+            Change::Removed(path) => removed.push(json!(path.to_string_lossy())),
+            Change::Modified(path) => modified.push(json!(path.to_string_lossy())),
+            Change::Renamed(old, new) => renamed.push(json!({
+                "from": old.to_string_lossy(),
+                "to": new.to_string_lossy(),
+            })),
+        }
+    }
+    json!({
+        "added": added,
+        "removed": removed,
+        "modified": modified,
+        "renamed": renamed,
+    })
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+    use crate::range::ByteRange;
+
+    fn record(file_name: &str, hash: &str) -> ChecksumRecord {
+        ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: file_name.into(),
+            binary: false,
+            hash: hash.repeat(32),
+            size: 0,
+            range: None,
+        }
+    }
+
+    #[test]
+    fn compare_manifests() {
+        let old = [
+            record("same", "a"),
+            record("changed", "b"),
+            record("old_name", "c"),
+            record("deleted", "d"),
+        ];
+        let new = [
+            record("same", "A"),
+            record("changed", "e"),
+            record("new_name", "c"),
+            record("created", "f"),
+        ];
+        assert_eq!(
+            vec![
+                Change::Modified("changed".into()),
+                Change::Added("created".into()),
+                Change::Removed("deleted".into()),
+                Change::Renamed("old_name".into(), "new_name".into()),
+            ],
+            compare(&old, &new)
+        );
+        assert!(compare(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn compare_ranges() {
+        let range = |file_name, offset, hash| ChecksumRecord {
+            range: Some(ByteRange { offset, length: 10 }),
+            ..record(file_name, hash)
+        };
+        // The ranges of a file are compared one by one
+        let old = [range("data.bin", 0, "a"), range("data.bin", 10, "b")];
+        let new = [
+            range("data.bin", 0, "a"),
+            range("data.bin", 10, "c"),
+            range("data.bin", 20, "d"),
+        ];
+        assert_eq!(
+            vec![
+                Change::Modified("data.bin@10+10".into()),
+                Change::Added("data.bin@20+10".into()),
+            ],
+            compare(&old, &new)
+        );
+        assert_eq!(
+            vec![Change::Removed("data.bin@10+10".into())],
+            compare(&old, &old[..1])
+        );
+    }
+
+    #[test]
+    fn compare_renamed_duplicates() {
+        // Each added copy is the new name of a single removed file
+        let old = [record("a", "1"), record("b", "1")];
+        let new = [record("c", "1")];
+        assert_eq!(
+            vec![
+                Change::Renamed("a".into(), "c".into()),
+                Change::Removed("b".into()),
+            ],
+            compare(&old, &new)
+        );
+    }
+
+    #[test]
+    fn changes_output() {
+        let changes = [
+            Change::Added("new".into()),
+            Change::Renamed("a".into(), "b".into()),
+        ];
+        assert_eq!("A  new", changes[0].to_text());
+        assert_eq!("R  a -> b", changes[1].to_text());
+        assert_eq!(
+            json!({
+                "added": ["new"],
+                "removed": [],
+                "modified": [],
+                "renamed": [{"from": "a", "to": "b"}],
+            }),
+            to_json(&changes)
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Hash algorithms supported by the checksum tools.
+
+use crate::legacy::{BsdSum, Cksum, SysvSum};
+
+/// Incremental hash computation, the data is fed with update
+/// and finalize returns the digest
+pub trait Digest {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+impl Digest for md5::Context {
+    fn update(&mut self, data: &[u8]) {
+        self.consume(data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        md5::Context::finalize(*self).0.to_vec()
+    }
+}
+
+impl Digest for Cksum {
+    fn update(&mut self, data: &[u8]) {
+        Cksum::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        Cksum::finalize(*self).to_be_bytes().to_vec()
+    }
+}
+
+impl Digest for BsdSum {
+    fn update(&mut self, data: &[u8]) {
+        BsdSum::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        BsdSum::finalize(*self).to_be_bytes().to_vec()
+    }
+}
+
+impl Digest for SysvSum {
+    fn update(&mut self, data: &[u8]) {
+        SysvSum::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        SysvSum::finalize(*self).to_be_bytes().to_vec()
+    }
+}
+
+/// Implements Digest for the hashes of the RustCrypto crates
+macro_rules! impl_digest {
+    ($($hash:ty),*) => {
+        $(
+            impl Digest for $hash {
+                fn update(&mut self, data: &[u8]) {
+                    sha2::Digest::update(self, data);
+                }
+
+                fn finalize(self: Box<Self>) -> Vec<u8> {
+                    sha2::Digest::finalize(*self).to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_digest!(
+    sha1::Sha1,
+    sha2::Sha224,
+    sha2::Sha256,
+    sha2::Sha384,
+    sha2::Sha512,
+    blake2::Blake2b512,
+    sm3::Sm3
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Sysv,
+    Bsd,
+    Crc,
+    Md5,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Blake2b,
+    Sm3,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 11] = [
+        Algorithm::Sysv,
+        Algorithm::Bsd,
+        Algorithm::Crc,
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha224,
+        Algorithm::Sha256,
+        Algorithm::Sha384,
+        Algorithm::Sha512,
+        Algorithm::Blake2b,
+        Algorithm::Sm3,
+    ];
+
+    /// Name used to select the algorithm in the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sysv => "sysv",
+            Algorithm::Bsd => "bsd",
+            Algorithm::Crc => "crc",
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha224 => "sha224",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake2b => "blake2b",
+            Algorithm::Sm3 => "sm3",
+        }
+    }
+
+    /// Tag used in the BSD style lines
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Algorithm::Sysv => "SYSV",
+            Algorithm::Bsd => "BSD",
+            Algorithm::Crc => "CRC",
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha224 => "SHA224",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha384 => "SHA384",
+            Algorithm::Sha512 => "SHA512",
+            Algorithm::Blake2b => "BLAKE2b",
+            Algorithm::Sm3 => "SM3",
+        }
+    }
+
+    /// The checksums of cksum and sum, printed as decimal numbers
+    /// with the size of the input and not supported by --check
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, Algorithm::Sysv | Algorithm::Bsd | Algorithm::Crc)
+    }
+
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        Algorithm::ALL.into_iter().find(|algo| algo.name() == name)
+    }
+
+    /// Size of the digest in bits
+    pub fn bits(&self) -> usize {
+        match self {
+            Algorithm::Sysv | Algorithm::Bsd => 16,
+            Algorithm::Crc => 32,
+            Algorithm::Md5 => 128,
+            Algorithm::Sha1 => 160,
+            Algorithm::Sha224 => 224,
+            Algorithm::Sha256 | Algorithm::Sm3 => 256,
+            Algorithm::Sha384 => 384,
+            Algorithm::Sha512 | Algorithm::Blake2b => 512,
+        }
+    }
+
+    pub fn new_digest(&self) -> Box<dyn Digest> {
+        match self {
+            Algorithm::Sysv => Box::new(SysvSum::default()),
+            Algorithm::Bsd => Box::new(BsdSum::default()),
+            Algorithm::Crc => Box::new(Cksum::new()),
+            Algorithm::Md5 => Box::new(md5::Context::new()),
+            Algorithm::Sha1 => Box::new(<sha1::Sha1 as sha2::Digest>::new()),
+            Algorithm::Sha224 => Box::new(<sha2::Sha224 as sha2::Digest>::new()),
+            Algorithm::Sha256 => Box::new(<sha2::Sha256 as sha2::Digest>::new()),
+            Algorithm::Sha384 => Box::new(<sha2::Sha384 as sha2::Digest>::new()),
+            Algorithm::Sha512 => Box::new(<sha2::Sha512 as sha2::Digest>::new()),
+            Algorithm::Blake2b => Box::new(<blake2::Blake2b512 as sha2::Digest>::new()),
+            Algorithm::Sm3 => Box::new(<sm3::Sm3 as sha2::Digest>::new()),
+        }
+    }
+}
+
+/// Returns the digest as a lowercase hexadecimal string
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod digest_tests {
+    use super::*;
+
+    fn hash(algo: Algorithm, data: &[u8]) -> String {
+        let mut digest = algo.new_digest();
+        digest.update(data);
+        to_hex(&digest.finalize())
+    }
+
+    // Test vectors of the GNU coreutils and the standards defining each algorithm
+    const VECTORS: [(Algorithm, &str, &str); 8] = [
+        (
+            Algorithm::Md5,
+            "d41d8cd98f00b204e9800998ecf8427e",
+            "900150983cd24fb0d6963f7d28e17f72",
+        ),
+        (
+            Algorithm::Sha1,
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            "a9993e364706816aba3e25717850c26c9cd0d89d",
+        ),
+        (
+            Algorithm::Sha224,
+            "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+        ),
+        (
+            Algorithm::Sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            Algorithm::Sha384,
+            "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+        ),
+        (
+            Algorithm::Sha512,
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        ),
+        (
+            Algorithm::Blake2b,
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce",
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+        ),
+        (
+            Algorithm::Sm3,
+            "1ab21d8355cfa17f8e61194831e81a8f22bec8c728fefb747ed035eb5082aa2b",
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0",
+        ),
+    ];
+
+    #[test]
+    fn known_vectors() {
+        for (algo, empty, abc) in VECTORS {
+            assert_eq!(empty, hash(algo, b""), "{}", algo.name());
+            assert_eq!(abc, hash(algo, b"abc"), "{}", algo.name());
+            assert_eq!(algo.bits() / 4, abc.len());
+        }
+    }
+
+    #[test]
+    fn legacy_checksums() {
+        // Values printed by GNU cksum -a {sysv,bsd,crc}
+        let inputs: [(&[u8], [&str; 3]); 3] = [
+            (b"", ["0000", "0000", "ffffffff"]),
+            (b"abc", ["0126", "40ac", "48aa78a2"]),
+            (b"hello world\n", ["0466", "0eb2", "de86ec5d"]),
+        ];
+        for (data, expected) in inputs {
+            assert_eq!(expected[0], hash(Algorithm::Sysv, data));
+            assert_eq!(expected[1], hash(Algorithm::Bsd, data));
+            assert_eq!(expected[2], hash(Algorithm::Crc, data));
+        }
+    }
+
+    #[test]
+    fn sm3_incremental_update() {
+        let expected = "debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732";
+        let data = b"abcd".repeat(16);
+        assert_eq!(expected, hash(Algorithm::Sm3, &data));
+
+        for chunk_size in [1, 3, 63, 64, 65] {
+            let mut digest = Algorithm::Sm3.new_digest();
+            for chunk in data.chunks(chunk_size) {
+                digest.update(chunk);
+            }
+            assert_eq!(expected, to_hex(&digest.finalize()));
+        }
+    }
+
+    #[test]
+    fn algorithm_names() {
+        for algo in Algorithm::ALL {
+            assert_eq!(Some(algo), Algorithm::from_name(algo.name()));
+        }
+        assert_eq!(None, Algorithm::from_name("md4"));
+    }
+}
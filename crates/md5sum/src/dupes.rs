@@ -0,0 +1,154 @@
+//! Duplicate file finder. The files are grouped by size, then by the digest
+//! of their first and last blocks, and only the files still grouped are
+//! hashed completely, so most of the files are never read to the end.
+
+use crate::digest::{Algorithm, to_hex};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Size of the blocks hashed at the start and at the end of the files
+const PARTIAL_BLOCK_SIZE: u64 = 4096;
+
+/// Files with the same contents
+#[derive(Debug, PartialEq)]
+pub struct DuplicateGroup {
+    pub digest: String,
+    pub size: u64,
+    /// The files in the input order, the actions keep the first one
+    pub files: Vec<OsString>,
+}
+
+/// Action taken on the duplicates of each group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateAction {
+    Hardlink,
+    DeleteKeepFirst,
+}
+
+/// Splits each group by the key of its items, the new groups are in the
+/// order of their first item. The items without key are dropped, as the
+/// groups left with a single item.
+pub fn split_groups<T, K: Eq + Hash>(
+    groups: Vec<Vec<T>>,
+    mut key: impl FnMut(&T) -> Option<K>,
+) -> Vec<Vec<T>> {
+    let mut output = vec![];
+    for group in groups {
+        let mut index = HashMap::<K, usize>::new();
+        let mut split: Vec<Vec<T>> = vec![];
+        for item in group {
+            let Some(item_key) = key(&item) else {
+                continue;
+            };
+            match index.get(&item_key) {
+                Some(&idx) => split[idx].push(item),
+                None => {
+                    index.insert(item_key, split.len());
+                    split.push(vec![item]);
+                }
+            }
+        }
+        output.extend(split.into_iter().filter(|group| group.len() > 1));
+    }
+    output
+}
+
+/// Returns the digest of the first and the last blocks of the file, the
+/// small files are hashed completely
+pub fn partial_digest(file_name: &Path, algo: Algorithm) -> io::Result<String> {
+    let mut file = File::open(file_name)?;
+    let size = file.metadata()?.len();
+    let mut processor = algo.new_digest();
+    let mut block = vec![];
+    (&mut file)
+        .take(PARTIAL_BLOCK_SIZE)
+        .read_to_end(&mut block)?;
+    processor.update(&block);
+    if size > PARTIAL_BLOCK_SIZE {
+        // The blocks don't overlap if the file is shorter than two blocks
+        let tail = size
+            .saturating_sub(PARTIAL_BLOCK_SIZE)
+            .max(PARTIAL_BLOCK_SIZE);
+        file.seek(SeekFrom::Start(tail))?;
+        block.clear();
+        file.take(PARTIAL_BLOCK_SIZE).read_to_end(&mut block)?;
+        processor.update(&block);
+    }
+    Ok(to_hex(&processor.finalize()))
+}
+
+/// Replaces the file with a hard link to the kept one. The link is created
+/// with a temporary name and renamed over the file, so the file is never
+/// missing, even if the link fails.
+pub fn hardlink(keep: &Path, file: &Path) -> io::Result<()> {
+    let mut temp_name = file.as_os_str().to_owned();
+    temp_name.push(format!(".{}.link", std::process::id()));
+    fs::hard_link(keep, &temp_name)?;
+    fs::rename(&temp_name, file).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_name);
+    })
+}
+
+/// Returns the groups as a JSON object with the digest type and a list of
+/// groups with their digest, size and files
+pub fn to_json(groups: &[DuplicateGroup], algo: Algorithm) -> Value {
+    let groups = groups
+        .iter()
+        .map(|group| {
+            json!({
+                "digest": group.digest,
+                "size": group.size,
+                "files": group
+                    .files
+                    .iter()
+                    .map(|file| file.to_string_lossy())
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "algorithm": algo.name(),
+        "groups": groups,
+    })
+}
+
+#[cfg(test)]
+mod dupes_tests {
+    use super::*;
+
+    #[test]
+    fn split_groups_by_key() {
+        let groups = vec![vec![1, 2, 3, 4, 5, 6, 7], vec![10, 20]];
+        // By the remainder, the multiples of 5 have no key and 2 is left alone
+        assert_eq!(
+            vec![vec![1, 4, 7], vec![3, 6]],
+            split_groups(groups, |&n| (n % 5 != 0).then_some(n % 3))
+        );
+    }
+
+    #[test]
+    fn partial_digest_of_head_and_tail() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dir = temp.path();
+        let write = |name: &str, middle: u8| {
+            let mut data = vec![b'a'; 4096];
+            data.extend_from_slice(&[middle; 100]);
+            data.extend_from_slice(&[b'z'; 4096]);
+            fs::write(dir.join(name), data).unwrap();
+            partial_digest(&dir.join(name), Algorithm::Md5).unwrap()
+        };
+        // Only the middle of the files is different
+        assert_eq!(write("a", b'1'), write("b", b'2'));
+
+        fs::write(dir.join("small"), "abc").unwrap();
+        assert_eq!(
+            "900150983cd24fb0d6963f7d28e17f72",
+            partial_digest(&dir.join("small"), Algorithm::Md5).unwrap()
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Encodings of the digests in the checksum lines. The digests are kept in
+//! hexadecimal and only encoded to write the lines, the lines read are
+//! decoded with any of the encodings.
+
+use crate::digest::{Algorithm, to_hex};
+use data_encoding::{BASE32, BASE64, HEXLOWER_PERMISSIVE};
+
+/// Encoding of the digests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    Base32,
+    /// Subresource Integrity, the algorithm name, a dash and the base64 digest
+    Sri,
+}
+
+/// Returns the hexadecimal digest of the algorithm in the encoding
+pub fn encode(hex: &str, algo: Algorithm, encoding: Encoding) -> String {
+    let Ok(digest) = HEXLOWER_PERMISSIVE.decode(hex.as_bytes()) else {
+        return hex.to_owned();
+    };
+    match encoding {
+        Encoding::Hex => hex.to_owned(),
+        Encoding::Base64 => BASE64.encode(&digest),
+        Encoding::Base32 => BASE32.encode(&digest),
+        Encoding::Sri => format!("{}-{}", algo.name(), BASE64.encode(&digest)),
+    }
+}
+
+/// Detects the encoding of the digest and returns the first of the
+/// algorithms with its length and the digest in hexadecimal. The SRI
+/// digests must be of one of the algorithms. The hexadecimal digits are
+/// tried first, then base64 and base32.
+pub fn decode(digest: &str, algorithms: &[Algorithm]) -> Option<(Algorithm, String)> {
+    if let Some((name, value)) = digest.split_once('-') {
+        let algo = Algorithm::from_name(name).filter(|algo| algorithms.contains(algo))?;
+        let bytes = BASE64.decode(value.as_bytes()).ok()?;
+        return (bytes.len() * 8 == algo.bits()).then(|| (algo, to_hex(&bytes)));
+    }
+
+    let with_length = |bytes: usize| algorithms.iter().find(|algo| algo.bits() == bytes * 8);
+    // The hexadecimal digits are kept as they are
+    if let Ok(bytes) = HEXLOWER_PERMISSIVE.decode(digest.as_bytes())
+        && let Some(&algo) = with_length(bytes.len())
+    {
+        return Some((algo, digest.to_owned()));
+    }
+    [BASE64, BASE32].iter().find_map(|decoder| {
+        let bytes = decoder.decode(digest.as_bytes()).ok()?;
+        with_length(bytes.len()).map(|&algo| (algo, to_hex(&bytes)))
+    })
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+
+    const ABC_MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+
+    #[test]
+    fn encode_digests() {
+        let md5 = Algorithm::Md5;
+        assert_eq!(ABC_MD5, encode(ABC_MD5, md5, Encoding::Hex));
+        assert_eq!(
+            "kAFQmDzST7DWlj99KOF/cg==",
+            encode(ABC_MD5, md5, Encoding::Base64)
+        );
+        assert_eq!(
+            "SAAVBGB42JH3BVUWH56SRYL7OI======",
+            encode(ABC_MD5, md5, Encoding::Base32)
+        );
+        assert_eq!(
+            "md5-kAFQmDzST7DWlj99KOF/cg==",
+            encode(ABC_MD5, md5, Encoding::Sri)
+        );
+    }
+
+    #[test]
+    fn decode_any_encoding() {
+        let all = Algorithm::ALL;
+        for encoding in [
+            Encoding::Hex,
+            Encoding::Base64,
+            Encoding::Base32,
+            Encoding::Sri,
+        ] {
+            let encoded = encode(ABC_MD5, Algorithm::Md5, encoding);
+            assert_eq!(
+                Some((Algorithm::Md5, ABC_MD5.to_owned())),
+                decode(&encoded, &all),
+                "{encoded}"
+            );
+        }
+        // The case of the hexadecimal digits is kept
+        let upper = ABC_MD5.to_ascii_uppercase();
+        assert_eq!(Some((Algorithm::Md5, upper.clone())), decode(&upper, &all));
+
+        // The first algorithm with the length of the digest
+        let sha256 = encode(&"ab".repeat(32), Algorithm::Sha256, Encoding::Base64);
+        assert_eq!(
+            Some(Algorithm::Sm3),
+            decode(&sha256, &[Algorithm::Sm3, Algorithm::Sha256]).map(|(algo, _)| algo)
+        );
+
+        assert_eq!(None, decode("sha1-kAFQmDzST7DWlj99KOF/cg==", &all));
+        assert_eq!(
+            None,
+            decode("md5-kAFQmDzST7DWlj99KOF/cg==", &[Algorithm::Sha1])
+        );
+        assert_eq!(None, decode("4e9cf98ae40b32b644119c74", &all));
+        assert_eq!(None, decode("not a digest", &all));
+    }
+}
@@ -0,0 +1,76 @@
+//! File name escaping of the checksum lines, as GNU coreutils. The names with
+//! a backslash, a newline or a carriage return are written with `\\`, `\n`
+//! and `\r`, and the line starts with a backslash to mark it as escaped.
+//! The `@` of a name that ends as a range suffix is written as `\@`, see
+//! range::ByteRange::escape_name.
+
+/// Returns the escaped name and whether any byte had to be escaped
+pub fn escape(name: &[u8]) -> (Vec<u8>, bool) {
+    let mut output = Vec::with_capacity(name.len());
+    let mut escaped = false;
+    for &byte in name {
+        match byte {
+            b'\\' => output.extend_from_slice(b"\\\\"),
+            b'\n' => output.extend_from_slice(b"\\n"),
+            b'\r' => output.extend_from_slice(b"\\r"),
+            _ => {
+                output.push(byte);
+                continue;
+            }
+        }
+        escaped = true;
+    }
+    (output, escaped)
+}
+
+/// Returns the name of an escaped line, or None if it has a backslash
+/// that doesn't start one of the escape sequences
+pub fn unescape(name: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(name.len());
+    let mut bytes = name.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != b'\\' {
+            output.push(byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'\\') => output.push(b'\\'),
+            Some(b'n') => output.push(b'\n'),
+            Some(b'r') => output.push(b'\r'),
+            Some(b'@') => output.push(b'@'),
+            _ => return None,
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod escape_tests {
+    use super::*;
+
+    #[test]
+    fn escape_special_bytes() {
+        assert_eq!((b"plain name".to_vec(), false), escape(b"plain name"));
+        assert_eq!((b"a\\nb".to_vec(), true), escape(b"a\nb"));
+        assert_eq!((b"a\\\\b\\r".to_vec(), true), escape(b"a\\b\r"));
+    }
+
+    #[test]
+    fn unescape_invalid_sequences() {
+        assert_eq!(None, unescape(b"a\\tb"));
+        assert_eq!(None, unescape(b"trailing\\"));
+        assert_eq!(Some(b"a\nb\\".to_vec()), unescape(b"a\\nb\\\\"));
+        assert_eq!(Some(b"x@1+2".to_vec()), unescape(b"x\\@1+2"));
+    }
+
+    #[test]
+    fn escape_round_trip() {
+        let every_byte = (1..=255).collect::<Vec<u8>>();
+        let names: [&[u8]; 5] = [b"\\", b"\\n", b"\n\n", b"a\r\nb", &every_byte];
+        for name in names {
+            let (escaped, _) = escape(name);
+            assert!(!escaped.contains(&b'\n') && !escaped.contains(&b'\r'));
+            assert_eq!(Some(name.to_vec()), unescape(&escaped));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Legacy checksums of the cksum and sum tools. They are not digests,
+//! the output is a decimal number and the size of the input.
+
+use crc::{CRC_32_CKSUM, Crc};
+
+static CKSUM: Crc<u32> = Crc::<u32>::new(&CRC_32_CKSUM);
+
+/// POSIX cksum CRC, the length of the input is hashed after the data
+pub struct Cksum {
+    digest: crc::Digest<'static, u32>,
+    length: u64,
+}
+
+impl Default for Cksum {
+    fn default() -> Self {
+        Cksum::new()
+    }
+}
+
+impl Cksum {
+    pub fn new() -> Cksum {
+        Cksum {
+            digest: CKSUM.digest(),
+            length: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.digest.update(data);
+        self.length += data.len() as u64;
+    }
+
+    pub fn finalize(mut self) -> u32 {
+        // The length goes least significant byte first, without the zero bytes
+        let mut length = self.length;
+        while length > 0 {
+            self.digest.update(&[length as u8]);
+            length >>= 8;
+        }
+        self.digest.finalize()
+    }
+}
+
+/// BSD sum, a 16 bit checksum rotated right before adding every byte
+#[derive(Debug, Default)]
+pub struct BsdSum {
+    checksum: u16,
+}
+
+impl BsdSum {
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.checksum = self.checksum.rotate_right(1).wrapping_add(byte as u16);
+        }
+    }
+
+    pub fn finalize(self) -> u16 {
+        self.checksum
+    }
+}
+
+/// System V sum, the sum of all the bytes folded to 16 bits
+#[derive(Debug, Default)]
+pub struct SysvSum {
+    sum: u64,
+}
+
+impl SysvSum {
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.sum = self.sum.wrapping_add(byte as u64);
+        }
+    }
+
+    pub fn finalize(self) -> u16 {
+        let r = (self.sum & 0xffff) + ((self.sum & 0xffff_ffff) >> 16);
+        ((r & 0xffff) + (r >> 16)) as u16
+    }
+}
@@ -0,0 +1,2008 @@
+mod cache;
+mod diff;
+pub mod digest;
+mod dupes;
+mod encoding;
+mod escape;
+mod legacy;
+mod progress;
+mod range;
+mod records;
+mod report;
+mod walk;
+
+use cache::{Cache, Fingerprint};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgGroup, command};
+use digest::{Algorithm, Digest, to_hex};
+use dupes::{DuplicateAction, DuplicateGroup};
+use encoding::Encoding;
+use progress::{Progress, ProgressReader};
+use range::{ByteRange, RangeSpec};
+use records::RecordWriter;
+use regex::bytes::Regex;
+use report::{Failure, Report, ReportFormat, TestCase};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, mpsc};
+use std::thread;
+use walk::WalkOptions;
+
+/// Default size of the buffer used to read the input files
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// BSD style lines of the check files, compiled once for all the lines.
+/// Without unicode the file names can have any byte.
+static TAGGED_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?-u)^([0-9A-Za-z]+) \((.+)\) = ([0-9A-Za-z+/=-]+)$").unwrap());
+/// GNU style lines of the check files
+static UNTAGGED_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?-u)^([0-9A-Za-z+/=-]+) ([ \*])([^\s\*].*)$").unwrap());
+
+/// Struct to hold the command line status
+///
+#[derive(Debug)]
+struct CommandLineFlags {
+    binary: bool,
+    tag: bool,
+    zero: bool,
+    /// Encoding of the digests written
+    encoding: Encoding,
+    check: bool,
+    /// Compare two manifests or directories
+    diff: bool,
+    /// Print the groups of files with the same contents
+    find_duplicates: bool,
+    /// Action taken on the duplicates found
+    duplicate_action: Option<DuplicateAction>,
+    /// Print the actions on the duplicates without doing them
+    dry_run: bool,
+    format: OutputFormat,
+    check_output: CheckOutput,
+    strict: bool,
+    ignore_missing: bool,
+    /// Report of the check results for the CI systems, and its file
+    report: Option<(ReportFormat, PathBuf)>,
+    relative_to_manifest: bool,
+    buffer_size: usize,
+    /// Number of threads hashing the files
+    jobs: usize,
+    /// Slice of the files hashed
+    range: Option<RangeSpec>,
+    /// Hash the files of the directories, with the walking options
+    recursive: Option<WalkOptions>,
+    /// Show the progress on stderr, only if it is a terminal
+    progress: bool,
+    /// File of the digests cache
+    cache: Option<PathBuf>,
+    /// Percentage of the cached files hashed again
+    cache_verify: u64,
+    algorithm: Algorithm,
+    /// The algorithm was selected with -a instead of the tool default
+    algorithm_selected: bool,
+}
+
+/// Format of the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+    /// A JSON object per line
+    Ndjson,
+    /// The groups of --find-duplicates as text
+    Groups,
+}
+
+/// Messages printed while verifying the check files
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CheckOutput {
+    /// Result of every file and the summary of the problems
+    Normal,
+    /// Don't print OK for the files verified successfully
+    Quiet,
+    /// Nothing, the exit status shows the result
+    Status,
+    /// Also warn about each improperly formatted line
+    Warn,
+}
+
+/// Parses the command line and returns a vector of
+/// files and a struct of command flags
+fn parse_input_args(
+    bin_name: &'static str,
+    algorithm: Algorithm,
+) -> (Vec<OsString>, CommandLineFlags) {
+    let matches = command!()
+        .name(bin_name)
+        .bin_name(bin_name)
+        .about(format!(
+            "Print or check {} ({}-bit) cheksums. ",
+            algorithm.tag(),
+            algorithm.bits()
+        ))
+        .arg(
+            Arg::new("FILE")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(OsString)),
+        )
+        .arg(
+            Arg::new("algorithm")
+                .short('a')
+                .long("algorithm")
+                .value_name("TYPE")
+                .value_parser(Algorithm::ALL.map(|algo| algo.name()))
+                .default_value(algorithm.name())
+                .help("Select the digest type to use"),
+        )
+        .arg(
+            Arg::new("binary")
+                .short('b')
+                .long("binary")
+                .action(ArgAction::SetTrue)
+                .help("Read in binary mode"),
+        )
+        .arg(
+            Arg::new("check")
+                .short('c')
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .help("Read checksums from the file and check them"),
+        )
+        .arg(
+            Arg::new("text")
+                .short('t')
+                .long("text")
+                .action(ArgAction::SetTrue)
+                .help("Read in text mode (default)"),
+        )
+        .arg(
+            Arg::new("tag")
+                .long("tag")
+                .action(ArgAction::SetTrue)
+                .help("Create a BSD style checksum"),
+        )
+        .arg(
+            Arg::new("untagged")
+                .long("untagged")
+                .action(ArgAction::SetTrue)
+                .overrides_with("tag")
+                .help("Create a reversed style checksum, without digest type"),
+        )
+        .arg(
+            Arg::new("zero")
+                .short('z')
+                .long("zero")
+                .action(ArgAction::SetTrue)
+                .help("End each output line with NUL, no newline, and disable file name scaping"),
+        )
+        .arg(
+            Arg::new("encoding")
+                .long("encoding")
+                .value_name("ENCODING")
+                .value_parser(["hex", "base64", "base32", "sri"])
+                .default_value("hex")
+                .help("Encoding of the digests written, --check detects it"),
+        )
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("check")
+                .help("Compare two checksum files, or a checksum file and a directory"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["text", "json", "csv", "ndjson", "groups"])
+                .default_value("text")
+                .help("Output format, groups prints the groups of --find-duplicates as text. --diff and --find-duplicates only support text and json"),
+        )
+        .arg(
+            Arg::new("find-duplicates")
+                .long("find-duplicates")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["check", "diff"])
+                .help("Print the groups of files with the same contents"),
+        )
+        .arg(
+            Arg::new("hardlink")
+                .long("hardlink")
+                .action(ArgAction::SetTrue)
+                .requires("find-duplicates")
+                .help("Replace the duplicates with hard links to the first file of each group"),
+        )
+        .arg(
+            Arg::new("delete-keep-first")
+                .long("delete-keep-first")
+                .action(ArgAction::SetTrue)
+                .requires("find-duplicates")
+                .conflicts_with("hardlink")
+                .help("Delete the duplicates but the first file of each group"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .requires("duplicate-action")
+                .help("Print the actions on the duplicates without doing them, needs --hardlink or --delete-keep-first"),
+        )
+        .arg(
+            Arg::new("ignore-missing")
+                .long("ignore-missing")
+                .action(ArgAction::SetTrue)
+                .requires("check")
+                .help("Don't fail or report status for missing files"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["status", "warn"])
+                .requires("check")
+                .help("Don't print OK for each successfully verified file"),
+        )
+        .arg(
+            Arg::new("status")
+                .long("status")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["quiet", "warn"])
+                .requires("check")
+                .help("Don't output anything, status code shows success"),
+        )
+        .arg(
+            Arg::new("relative-to-manifest")
+                .long("relative-to-manifest")
+                .action(ArgAction::SetTrue)
+                .requires("check")
+                .help("Resolve the listed files relative to the directory of each checksum file"),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("FORMAT")
+                .value_parser(["tap", "junit"])
+                .requires_all(["check", "report-file"])
+                .help("Write a report of the check results with a test case per line"),
+        )
+        .arg(
+            Arg::new("report-file")
+                .long("report-file")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .requires("report")
+                .help("File of the --report"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .requires("check")
+                .help("Exit non-zero for improperly formatted checksum lines"),
+        )
+        .arg(
+            Arg::new("warn")
+                .short('w')
+                .long("warn")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["quiet", "status"])
+                .requires("check")
+                .help("Warn about improperly formatted checksum lines"),
+        )
+        .arg(
+            Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("check")
+                .help("Hash the files of the directories, named relative to each directory"),
+        )
+        .arg(
+            Arg::new("include")
+                .long("include")
+                .value_name("GLOB")
+                .action(ArgAction::Append)
+                .requires("recursive")
+                .help("Only hash the files matching GLOB, can be repeated"),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .value_name("GLOB")
+                .action(ArgAction::Append)
+                .requires("recursive")
+                .help("Skip the files and directories matching GLOB, can be repeated"),
+        )
+        .arg(
+            Arg::new("follow-symlinks")
+                .long("follow-symlinks")
+                .action(ArgAction::SetTrue)
+                .requires("recursive")
+                .help("Walk the directories of the symbolic links, the links to files are always hashed"),
+        )
+        .arg(
+            Arg::new("one-file-system")
+                .long("one-file-system")
+                .action(ArgAction::SetTrue)
+                .requires("recursive")
+                .help("Don't walk the directories on other file systems"),
+        )
+        .arg(
+            Arg::new("gitignore")
+                .long("gitignore")
+                .action(ArgAction::SetTrue)
+                .requires("recursive")
+                .help("Skip the files ignored by .gitignore"),
+        )
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Reuse the digests stored in FILE for the files that didn't change"),
+        )
+        .arg(
+            Arg::new("cache-verify")
+                .long("cache-verify")
+                .value_name("PERCENT")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("10")
+                .value_parser(clap::value_parser!(u64).range(0..=100))
+                .requires("cache")
+                .help("Hash again a random sample of the cached files, 10% by default"),
+        )
+        .arg(
+            Arg::new("offset")
+                .long("offset")
+                .value_name("BYTES")
+                .value_parser(range::parse_size)
+                .conflicts_with_all(["check", "diff", "find-duplicates", "head", "tail"])
+                .help("Hash the files from BYTES, with a K, M, G or T suffix"),
+        )
+        .arg(
+            Arg::new("length")
+                .long("length")
+                .value_name("BYTES")
+                .value_parser(range::parse_size)
+                .conflicts_with_all(["check", "diff", "find-duplicates", "head", "tail"])
+                .help("Hash up to BYTES of the files"),
+        )
+        .arg(
+            Arg::new("head")
+                .long("head")
+                .value_name("BYTES")
+                .value_parser(range::parse_size)
+                .conflicts_with_all(["check", "diff", "find-duplicates", "tail"])
+                .help("Hash the first BYTES of the files"),
+        )
+        .arg(
+            Arg::new("tail")
+                .long("tail")
+                .value_name("BYTES")
+                .value_parser(range::parse_size)
+                .conflicts_with_all(["check", "diff", "find-duplicates"])
+                .help("Hash the last BYTES of the files"),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .action(ArgAction::SetTrue)
+                .help("Show the progress on stderr, if it is a terminal"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..=1024))
+                .help("Hash up to N files at the same time, by default the number of CPUs"),
+        )
+        .arg(
+            Arg::new("buffer-size")
+                .long("buffer-size")
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(u64).range(1..=1 << 30))
+                .help("Size of the buffer used to read the input files"),
+        )
+        .group(ArgGroup::new("duplicate-action").args(["hardlink", "delete-keep-first"]))
+        .get_matches();
+
+    let mut input_files = matches
+        .get_many::<OsString>("FILE")
+        .unwrap_or_default()
+        .map(|x| x.to_owned())
+        .collect::<Vec<OsString>>();
+
+    if input_files.is_empty() {
+        input_files.push("-".into());
+    }
+
+    let flags = CommandLineFlags {
+        binary: matches.get_flag("binary"),
+        // cksum prints BSD style lines by default, as coreutils 9
+        tag: matches.get_flag("tag") || (bin_name == "cksum" && !matches.get_flag("untagged")),
+        zero: matches.get_flag("zero"),
+        encoding: match matches.get_one::<String>("encoding").map(|x| x.as_str()) {
+            Some("base64") => Encoding::Base64,
+            Some("base32") => Encoding::Base32,
+            Some("sri") => Encoding::Sri,
+            _ => Encoding::Hex,
+        },
+        check: matches.get_flag("check"),
+        diff: matches.get_flag("diff"),
+        find_duplicates: matches.get_flag("find-duplicates"),
+        duplicate_action: if matches.get_flag("hardlink") {
+            Some(DuplicateAction::Hardlink)
+        } else if matches.get_flag("delete-keep-first") {
+            Some(DuplicateAction::DeleteKeepFirst)
+        } else {
+            None
+        },
+        dry_run: matches.get_flag("dry-run"),
+        format: match matches.get_one::<String>("format").map(|x| x.as_str()) {
+            Some("json") => OutputFormat::Json,
+            Some("csv") => OutputFormat::Csv,
+            Some("ndjson") => OutputFormat::Ndjson,
+            Some("groups") => OutputFormat::Groups,
+            _ => OutputFormat::Text,
+        },
+        check_output: if matches.get_flag("quiet") {
+            CheckOutput::Quiet
+        } else if matches.get_flag("status") {
+            CheckOutput::Status
+        } else if matches.get_flag("warn") {
+            CheckOutput::Warn
+        } else {
+            CheckOutput::Normal
+        },
+        strict: matches.get_flag("strict"),
+        ignore_missing: matches.get_flag("ignore-missing"),
+        report: matches
+            .get_one::<String>("report")
+            .zip(matches.get_one::<PathBuf>("report-file"))
+            .map(|(format, path)| {
+                let format = match format.as_str() {
+                    "junit" => ReportFormat::Junit,
+                    _ => ReportFormat::Tap,
+                };
+                (format, path.clone())
+            }),
+        relative_to_manifest: matches.get_flag("relative-to-manifest"),
+        buffer_size: matches
+            .get_one::<u64>("buffer-size")
+            .map_or(DEFAULT_BUFFER_SIZE, |&size| size as usize),
+        jobs: matches.get_one::<u64>("jobs").map_or_else(
+            || thread::available_parallelism().map_or(1, |n| n.get()),
+            |&jobs| jobs as usize,
+        ),
+        range: if let Some(&length) = matches.get_one::<u64>("head") {
+            Some(RangeSpec::Slice {
+                offset: 0,
+                length: Some(length),
+            })
+        } else if let Some(&length) = matches.get_one::<u64>("tail") {
+            Some(RangeSpec::Tail(length))
+        } else if matches.contains_id("offset") || matches.contains_id("length") {
+            Some(RangeSpec::Slice {
+                offset: matches.get_one::<u64>("offset").copied().unwrap_or(0),
+                length: matches.get_one::<u64>("length").copied(),
+            })
+        } else {
+            None
+        },
+        progress: matches.get_flag("progress") && io::stderr().is_terminal(),
+        cache: matches.get_one::<PathBuf>("cache").cloned(),
+        cache_verify: matches.get_one::<u64>("cache-verify").copied().unwrap_or(0),
+        recursive: matches.get_flag("recursive").then(|| WalkOptions {
+            include: matches
+                .get_many::<String>("include")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            exclude: matches
+                .get_many::<String>("exclude")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            follow_symlinks: matches.get_flag("follow-symlinks"),
+            one_file_system: matches.get_flag("one-file-system"),
+            gitignore: matches.get_flag("gitignore"),
+        }),
+        algorithm: matches
+            .get_one::<String>("algorithm")
+            .and_then(|name| Algorithm::from_name(name))
+            .unwrap_or(algorithm),
+        algorithm_selected: matches.value_source("algorithm") == Some(ValueSource::CommandLine),
+    };
+
+    (input_files, flags)
+}
+
+/// Line number and error of an improperly formatted line
+type MalformedLine = (usize, String);
+
+/// Opens the file passed as argument and parses the contents
+/// Returns a vector of file names ans expected hashes and the line number
+/// and error of the improperly formatted lines, or error if there isn't any correct line
+/// or cannot read the input file. Empty lines and lines starting with #
+/// are skipped. The improperly formatted lines are reported if warn is set.
+fn parse_check_file(
+    file_name: &OsStr,
+    algorithm: Algorithm,
+    warn: bool,
+) -> Result<(Vec<ChecksumRecord>, Vec<MalformedLine>), Box<dyn Error>> {
+    let mut output = vec![];
+    let mut malformed = vec![];
+    let data = if file_name == "-" {
+        let mut data = vec![];
+        io::stdin().read_to_end(&mut data)?;
+        data
+    } else {
+        fs::read(file_name)?
+    };
+    let data = data.strip_suffix(b"\n").unwrap_or(&data);
+    for (idx, line) in data.split(|&byte| byte == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        match parse_line(line, algorithm) {
+            Ok(check_line) => {
+                output.push(check_line);
+            }
+            Err(e) => {
+                if warn {
+                    eprintln!(
+                        "{}: {}: improperly formatted checksum line\n\t{}",
+                        file_name.display(),
+                        idx + 1,
+                        e
+                    );
+                }
+                malformed.push((idx + 1, e.to_string()));
+            }
+        }
+    }
+    if !output.is_empty() {
+        Ok((output, malformed))
+    } else {
+        Err(format!(
+            "{}: no properly formatted checksum lines found",
+            file_name.display()
+        )
+        .into())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ChecksumRecord {
+    algo: Algorithm,
+    file_name: OsString,
+    binary: bool,
+    hash: String,
+    /// Size of the input in bytes
+    size: u64,
+    /// Slice of the file hashed, the whole file if None
+    range: Option<ByteRange>,
+}
+
+impl ChecksumRecord {
+    /// Returns the file name with the suffix of the range, if any
+    fn display_name(&self) -> OsString {
+        let mut name = self.file_name.clone();
+        if let Some(range) = self.range {
+            name.push(range.suffix());
+        }
+        name
+    }
+}
+
+/// Parses a line of a check file in any of the two formats
+/// BSD style: TAG + space + (file name) + space + = + space + digest
+/// GNU style: digest + space + [space|*] + str
+/// The digests can be hexadecimal, base64, base32 or SRI, see encoding::decode.
+/// The algorithm of the BSD style lines is selected by the tag, the GNU style
+/// ones use algo if the digest has its length, or any other with that length.
+/// The lines starting with a backslash have an escaped file name, and the
+/// names ending with @OFFSET+LENGTH are a slice of the file, unless the @
+/// is escaped.
+fn parse_line(line: &[u8], algo: Algorithm) -> Result<ChecksumRecord, Box<dyn Error>> {
+    let printable = String::from_utf8_lossy(line);
+    let (escaped, line) = match line.strip_prefix(b"\\") {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+
+    let (algo, file_name, binary, hash) = if let Some(caps) = TAGGED_LINE.captures(line) {
+        let Some(algo) = Algorithm::ALL
+            .into_iter()
+            .find(|algo| !algo.is_legacy() && algo.tag().as_bytes() == &caps[1])
+        else {
+            return Err(format!("Unknown digest type in {}", printable).into());
+        };
+        // The digest only has ASCII characters
+        let Some((algo, hash)) = encoding::decode(&String::from_utf8_lossy(&caps[3]), &[algo])
+        else {
+            return Err(format!("Wrong {} digest in {}", algo.tag(), printable).into());
+        };
+        // The BSD style lines are always generated in binary mode
+        (algo, caps[2].to_vec(), true, hash)
+    } else if let Some(caps) = UNTAGGED_LINE.captures(line) {
+        let algorithms = [algo]
+            .into_iter()
+            .chain(Algorithm::ALL)
+            .filter(|algo| !algo.is_legacy())
+            .collect::<Vec<_>>();
+        let Some((algo, hash)) = encoding::decode(&String::from_utf8_lossy(&caps[1]), &algorithms)
+        else {
+            return Err(format!("No digest type or encoding matches {}", printable).into());
+        };
+        (algo, caps[3].to_vec(), &caps[2] == b"*", hash)
+    } else {
+        return Err(format!("Cannot parse {}", printable).into());
+    };
+    let (file_name, range) = match ByteRange::split_name(&file_name) {
+        Some((name, range)) => (name.to_vec(), Some(range)),
+        None => (file_name, None),
+    };
+    let file_name = if escaped {
+        escape::unescape(&file_name)
+            .ok_or_else(|| format!("Invalid escape sequence in {}", printable))?
+    } else {
+        file_name
+    };
+
+    let output = ChecksumRecord {
+        algo,
+        file_name: OsString::from_vec(file_name),
+        binary,
+        hash,
+        // The size is not recorded in the check files
+        size: 0,
+        range,
+    };
+
+    Ok(output)
+}
+
+/// Result of verifying one of the files listed in a check file
+#[derive(Debug, PartialEq)]
+enum CheckResult {
+    Ok,
+    /// The digest of the file doesn't match, with the digest computed
+    Mismatch(String),
+    /// The file doesn't exist, with the error message
+    Missing(String),
+    /// The file couldn't be opened or read, with the error message
+    ReadError(String),
+}
+
+impl CheckResult {
+    /// Prints the result of the file, OK is omitted if quiet is set.
+    /// The file name is escaped as in the checksum lines.
+    fn print(&self, file_name: &OsStr, quiet: bool) -> io::Result<()> {
+        let message = match self {
+            CheckResult::Ok if quiet => return Ok(()),
+            CheckResult::Ok => "OK",
+            CheckResult::Mismatch(_) => "FAILED",
+            CheckResult::Missing(e) | CheckResult::ReadError(e) => {
+                eprintln!(
+                    "Some error happened reading file: {}: {}",
+                    file_name.display(),
+                    e
+                );
+                "FAILED open or read"
+            }
+        };
+        let (name, escaped) = escape::escape(file_name.as_bytes());
+        let mut handle = io::stdout().lock();
+        if escaped {
+            handle.write_all(b"\\")?;
+        }
+        handle.write_all(&name)?;
+        writeln!(handle, ": {}", message)
+    }
+
+    /// Returns the result of the record as a test case of the report
+    fn to_test_case(&self, record: &ChecksumRecord) -> TestCase {
+        let failure = |kind, message| {
+            Some(Failure {
+                kind,
+                message,
+                expected: Some(record.hash.clone()),
+                actual: None,
+            })
+        };
+        let failure = match self {
+            CheckResult::Ok => None,
+            CheckResult::Mismatch(actual) => {
+                failure("mismatch", "computed checksum did NOT match".to_owned()).map(|failure| {
+                    Failure {
+                        actual: Some(actual.clone()),
+                        ..failure
+                    }
+                })
+            }
+            CheckResult::Missing(e) => failure("missing", format!("listed file is missing: {}", e)),
+            CheckResult::ReadError(e) => failure(
+                "read-error",
+                format!("listed file could not be read: {}", e),
+            ),
+        };
+        TestCase {
+            name: record.display_name().to_string_lossy().into_owned(),
+            failure,
+        }
+    }
+
+    /// Returns the result of the record as an object with the fields of
+    /// records::CHECK_FIELDS
+    fn to_json(&self, record: &ChecksumRecord) -> Value {
+        let (actual, status, error) = match self {
+            CheckResult::Ok => (Some(record.hash.as_str()), "ok", None),
+            CheckResult::Mismatch(actual) => (Some(actual.as_str()), "failed", None),
+            CheckResult::Missing(e) => (None, "missing", Some(e)),
+            CheckResult::ReadError(e) => (None, "error", Some(e)),
+        };
+        json!({
+            "path": record.display_name().to_string_lossy(),
+            "expected": record.hash,
+            "actual": actual,
+            "status": status,
+            "error": error,
+        })
+    }
+}
+
+/// Hashes the file of the record and compares only the digests, ignoring
+/// the case of the hexadecimal digits. The binary flag doesn't change the
+/// digest, so it is not compared. The range of the record must be in the file.
+fn verify_record(
+    record: &ChecksumRecord,
+    buffer_size: usize,
+    cache: Option<&Cache>,
+    progress: Option<&Progress>,
+) -> CheckResult {
+    match get_cached_record(
+        &record.file_name,
+        record.binary,
+        record.algo,
+        buffer_size,
+        record.range.map(RangeSpec::from).as_ref(),
+        cache,
+        progress,
+    ) {
+        Ok(computed) if computed.range != record.range => {
+            CheckResult::ReadError("the file ends before the range".to_owned())
+        }
+        Ok(computed) if computed.hash.eq_ignore_ascii_case(&record.hash) => CheckResult::Ok,
+        Ok(computed) => CheckResult::Mismatch(computed.hash),
+        Err(e) => {
+            let missing = e
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::NotFound);
+            if missing {
+                CheckResult::Missing(e.to_string())
+            } else {
+                CheckResult::ReadError(e.to_string())
+            }
+        }
+    }
+}
+
+/// Number of problems found verifying a check file
+#[derive(Debug, Default, PartialEq)]
+struct CheckSummary {
+    malformed: usize,
+    mismatched: usize,
+    unreadable: usize,
+    verified: usize,
+}
+
+impl CheckSummary {
+    fn add(&mut self, result: &CheckResult) {
+        match result {
+            CheckResult::Ok => self.verified += 1,
+            CheckResult::Mismatch(_) => {
+                self.verified += 1;
+                self.mismatched += 1;
+            }
+            CheckResult::Missing(_) | CheckResult::ReadError(_) => self.unreadable += 1,
+        }
+    }
+
+    /// Prints the warnings of the problems found, in the GNU coreutils format
+    fn print_warnings(&self) {
+        let plural = |n: usize, one: &str, many: &str| {
+            if n == 1 {
+                format!("{n} {one}")
+            } else {
+                format!("{n} {many}")
+            }
+        };
+        if self.malformed > 0 {
+            eprintln!(
+                "WARNING: {} improperly formatted",
+                plural(self.malformed, "line is", "lines are")
+            );
+        }
+        if self.unreadable > 0 {
+            eprintln!(
+                "WARNING: {} could not be read",
+                plural(self.unreadable, "listed file", "listed files")
+            );
+        }
+        if self.mismatched > 0 {
+            eprintln!(
+                "WARNING: {} did NOT match",
+                plural(self.mismatched, "computed checksum", "computed checksums")
+            );
+        }
+    }
+
+    /// The verification fails with any mismatched or unreadable file, with
+    /// improperly formatted lines in strict mode, or if no file was verified
+    fn is_success(&self, strict: bool) -> bool {
+        self.mismatched == 0
+            && self.unreadable == 0
+            && (!strict || self.malformed == 0)
+            && self.verified > 0
+    }
+
+    /// Adds the problems found in another check file
+    fn merge(&mut self, other: &CheckSummary) {
+        self.malformed += other.malformed;
+        self.mismatched += other.mismatched;
+        self.unreadable += other.unreadable;
+        self.verified += other.verified;
+    }
+
+    fn to_json(&self, success: bool) -> Value {
+        json!({
+            "verified": self.verified,
+            "failed": self.mismatched,
+            "unreadable": self.unreadable,
+            "malformed": self.malformed,
+            "success": success,
+        })
+    }
+}
+
+/// Returns the path of a file listed in the check file. With relative_to_manifest
+/// the relative paths start from the directory of the check file, instead of the
+/// current directory. The paths of the check files read from stdin are not changed.
+fn resolve_listed_file(check_file: &OsStr, listed: &OsStr, relative_to_manifest: bool) -> OsString {
+    if !relative_to_manifest || check_file == "-" || listed == "-" {
+        return listed.to_owned();
+    }
+    match Path::new(check_file).parent() {
+        // join keeps the absolute paths as they are
+        Some(dir) => dir.join(listed).into_os_string(),
+        None => listed.to_owned(),
+    }
+}
+
+/// Verifies the files listed in the check file, printing the result of each
+/// one and the summary as selected by the flags. With a writer the results
+/// are written as records and the summary is left to the caller. The
+/// results and the improperly formatted lines are added to the report.
+fn check(
+    file_name: &OsStr,
+    flags: &CommandLineFlags,
+    cache: Option<&Cache>,
+    mut writer: Option<&mut RecordWriter<io::Stdout>>,
+    report: Option<&mut Report>,
+) -> Result<CheckSummary, Box<dyn Error>> {
+    let (records, malformed) = parse_check_file(
+        file_name,
+        flags.algorithm,
+        flags.check_output == CheckOutput::Warn,
+    )?;
+    let status = flags.check_output == CheckOutput::Status;
+    let mut summary = CheckSummary {
+        malformed: malformed.len(),
+        ..Default::default()
+    };
+    let mut cases = vec![];
+    let records = records
+        .into_iter()
+        .map(|mut record| {
+            record.file_name =
+                resolve_listed_file(file_name, &record.file_name, flags.relative_to_manifest);
+            record
+        })
+        .collect::<Vec<_>>();
+    let progress = flags.progress.then(|| {
+        Progress::new(records.iter().map(|record| {
+            (
+                record.file_name.as_os_str(),
+                record.range.map(RangeSpec::from),
+            )
+        }))
+    });
+    progress::run(progress.as_ref(), || -> Result<(), Box<dyn Error>> {
+        for record in records.iter() {
+            if let Some(progress) = progress.as_ref() {
+                progress.start_file(&record.file_name);
+            }
+            let result = verify_record(record, flags.buffer_size, cache, progress.as_ref());
+            if let Some(progress) = progress.as_ref() {
+                progress.finish_file();
+            }
+            if flags.ignore_missing && matches!(result, CheckResult::Missing(_)) {
+                continue;
+            }
+            summary.add(&result);
+            if report.is_some() {
+                cases.push(result.to_test_case(record));
+            }
+            // The status line is cleared while the result is written
+            let _hidden = progress.as_ref().map(|progress| progress.hide());
+            let quiet = flags.check_output == CheckOutput::Quiet;
+            match writer.as_mut() {
+                _ if status => {}
+                Some(_) if quiet && result == CheckResult::Ok => {}
+                Some(writer) => writer.write(result.to_json(record))?,
+                None => result.print(&record.display_name(), quiet)?,
+            }
+        }
+        Ok(())
+    })?;
+
+    if let Some(report) = report {
+        cases.extend(malformed.into_iter().map(|(line, e)| TestCase {
+            name: format!("line {}", line),
+            failure: Some(Failure {
+                kind: "parse-error",
+                message: format!("improperly formatted checksum line: {}", e),
+                expected: None,
+                actual: None,
+            }),
+        }));
+        report.add_suite(file_name.to_string_lossy().into_owned(), cases);
+    }
+
+    if !status && writer.is_none() {
+        summary.print_warnings();
+        if summary.verified == 0 && flags.ignore_missing {
+            eprintln!("{}: no file was verified", file_name.display());
+        }
+    }
+    Ok(summary)
+}
+
+/// Feeds the data of the reader to the hash processor in blocks of
+/// buffer_size bytes, so the memory used does not depend on the input size
+fn hash_reader(
+    reader: &mut impl Read,
+    mut processor: Box<dyn Digest>,
+    buffer_size: usize,
+) -> io::Result<(Vec<u8>, u64)> {
+    let mut buffer = vec![0; buffer_size];
+    let mut size = 0;
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => {
+                processor.update(&buffer[..n]);
+                size += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((processor.finalize(), size))
+}
+
+/// Hashes the file, or stdin if the file name is -. The data is hashed
+/// as raw bytes in both modes, as on Linux text and binary mode read the
+/// same bytes, the binary flag only selects the marker used in the output.
+/// With a range only that slice is hashed, the range of the record has the
+/// offset and the bytes hashed. The bytes read are added to the progress.
+fn get_checksum_record(
+    file_name: &OsStr,
+    binary: bool,
+    algo: Algorithm,
+    buffer_size: usize,
+    range: Option<&RangeSpec>,
+    progress: Option<&Progress>,
+) -> Result<ChecksumRecord, Box<dyn Error>> {
+    let processor = algo.new_digest();
+    let (input, offset) = range::open(file_name, range)?;
+    let mut reader = ProgressReader::new(input, progress);
+    let (digest, size) = hash_reader(&mut reader, processor, buffer_size)?;
+    Ok(ChecksumRecord {
+        algo,
+        file_name: file_name.to_owned(),
+        binary,
+        hash: to_hex(&digest),
+        size,
+        range: range.map(|_| ByteRange {
+            offset,
+            length: size,
+        }),
+    })
+}
+
+/// Hashes the file as get_checksum_record, but takes the digest from the
+/// cache if the file didn't change. The files of the verification sample are
+/// hashed again, a wrong cached digest is reported and counted as an error.
+/// The cache only has the digests of whole files, the ranges are hashed.
+fn get_cached_record(
+    file_name: &OsStr,
+    binary: bool,
+    algo: Algorithm,
+    buffer_size: usize,
+    range: Option<&RangeSpec>,
+    cache: Option<&Cache>,
+    progress: Option<&Progress>,
+) -> Result<ChecksumRecord, Box<dyn Error>> {
+    let Some(cache) = cache.filter(|_| file_name != "-" && range.is_none()) else {
+        return get_checksum_record(file_name, binary, algo, buffer_size, range, progress);
+    };
+    let key = cache::cache_key(file_name)?;
+    let fingerprint = Fingerprint::of(&key)?;
+    let cached = cache.get(&key, algo, &fingerprint);
+    if let Some(hash) = cached
+        && !cache.is_sampled(&key)
+    {
+        if let Some(progress) = progress {
+            progress.add_bytes(fingerprint.size);
+        }
+        return Ok(ChecksumRecord {
+            algo,
+            file_name: file_name.to_owned(),
+            binary,
+            hash: hash.to_owned(),
+            size: fingerprint.size,
+            range: None,
+        });
+    }
+
+    let record = get_checksum_record(file_name, binary, algo, buffer_size, None, progress)?;
+    if cached.is_some_and(|hash| hash != record.hash) {
+        eprintln!(
+            "The cached digest of {} doesn't match the file",
+            file_name.display()
+        );
+        cache.add_mismatch();
+    }
+    cache.insert(&key, algo, fingerprint, &record.hash);
+    Ok(record)
+}
+
+/// Retuns a formatted line with the
+/// file name a * if is binary input and the hash,
+/// if tags is to True generated BDS style output.
+/// With escape set the file names with special characters are
+/// escaped and the line starts with a backslash. The digest is
+/// written in the encoding.
+fn format_output_line(
+    record: &ChecksumRecord,
+    tag: bool,
+    escape: bool,
+    encoding: Encoding,
+) -> Vec<u8> {
+    if record.algo.is_legacy() {
+        return format_legacy_line(record);
+    }
+    let name = record.display_name();
+    let (mut file_name, mut escaped) = if escape {
+        escape::escape(name.as_bytes())
+    } else {
+        (name.as_bytes().to_vec(), false)
+    };
+    // A whole file named as a range is not read back as a slice of the file
+    if escape && record.range.is_none() {
+        escaped |= ByteRange::escape_name(&mut file_name);
+    }
+    let digest = encoding::encode(&record.hash, record.algo, encoding);
+    let mut output = if escaped { b"\\".to_vec() } else { vec![] };
+    if tag {
+        output.extend_from_slice(format!("{} (", record.algo.tag()).as_bytes());
+        output.extend_from_slice(&file_name);
+        output.extend_from_slice(format!(") = {}", digest).as_bytes());
+    } else {
+        let binary_char = if record.binary { "*" } else { " " };
+        output.extend_from_slice(format!("{} {}", digest, binary_char).as_bytes());
+        output.extend_from_slice(&file_name);
+    }
+    output
+}
+
+/// Returns the line of the cksum and sum checksums, the decimal value,
+/// the size in bytes or blocks and the file name, omitted for stdin
+fn format_legacy_line(record: &ChecksumRecord) -> Vec<u8> {
+    // The records are hashed or read from the cache, which checks the digests
+    let value = u32::from_str_radix(&record.hash, 16)
+        .expect("the legacy checksums are 16 or 32 bit hexadecimal values");
+    let mut output = match record.algo {
+        Algorithm::Bsd => format!("{:05} {:5}", value, record.size.div_ceil(1024)),
+        Algorithm::Sysv => format!("{} {}", value, record.size.div_ceil(512)),
+        _ => format!("{} {}", value, record.size),
+    }
+    .into_bytes();
+    if record.file_name != "-" {
+        output.push(b' ');
+        output.extend_from_slice(record.display_name().as_bytes());
+    }
+    output
+}
+
+/// Hashes the input files in flags.jobs threads and calls output with the
+/// index and the result of every file, in the same order as the input files. Each file is
+/// hashed by a single thread, so while one thread hashes a big file the others
+/// keep taking the next small ones. The progress is shown while hashing.
+fn hash_files<F>(
+    input_files: &[OsString],
+    algorithm: Algorithm,
+    flags: &CommandLineFlags,
+    cache: Option<&Cache>,
+    progress: Option<&Progress>,
+    mut output: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(usize, Result<ChecksumRecord, String>) -> Result<(), Box<dyn Error>>,
+{
+    let next_file = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    progress::run(progress, || {
+        thread::scope(|scope| {
+            for _ in 0..flags.jobs.min(input_files.len()) {
+                let sender = sender.clone();
+                let next_file = &next_file;
+                scope.spawn(move || {
+                    loop {
+                        let idx = next_file.fetch_add(1, Ordering::Relaxed);
+                        let Some(file_name) = input_files.get(idx) else {
+                            break;
+                        };
+                        if let Some(progress) = progress {
+                            progress.start_file(file_name);
+                        }
+                        let record = get_cached_record(
+                            file_name,
+                            flags.binary,
+                            algorithm,
+                            flags.buffer_size,
+                            flags.range.as_ref(),
+                            cache,
+                            progress,
+                        )
+                        .map_err(|e| e.to_string());
+                        if let Some(progress) = progress {
+                            progress.finish_file();
+                        }
+                        // The receiver is gone if the output failed
+                        if sender.send((idx, record)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // The results that arrive before the previous files are kept until printed
+            let mut pending = BTreeMap::new();
+            let mut next_output = 0;
+            for (idx, record) in receiver {
+                pending.insert(idx, record);
+                while let Some(record) = pending.remove(&next_output) {
+                    output(next_output, record)?;
+                    next_output += 1;
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+fn print_output(
+    input_files: &[OsString],
+    flags: &CommandLineFlags,
+    cache: Option<&Cache>,
+) -> Result<(), Box<dyn Error>> {
+    let mut error_counter = 0;
+    // The paths of the files to hash and the names printed
+    let (paths, names): (Vec<_>, Vec<_>) = match &flags.recursive {
+        Some(options) => {
+            let (files, errors) = walk::walk(input_files, options)?;
+            error_counter += errors;
+            files.into_iter().unzip()
+        }
+        None => (input_files.to_vec(), input_files.to_vec()),
+    };
+
+    let output = io::BufWriter::new(io::stdout());
+    let mut writer = RecordWriter::new(flags.format, records::HASH_FIELDS, output)?;
+    let mut handle = io::BufWriter::new(io::stdout());
+    let progress = flags
+        .progress
+        .then(|| Progress::new(paths.iter().map(|path| (path.as_os_str(), flags.range))));
+    hash_files(
+        &paths,
+        flags.algorithm,
+        flags,
+        cache,
+        progress.as_ref(),
+        |idx, record| {
+            // The status line is cleared while the output is written
+            let _hidden = progress.as_ref().map(|progress| progress.hide());
+            match record {
+                Ok(mut record) => {
+                    record.file_name = names[idx].clone();
+                    if let Some(writer) = writer.as_mut() {
+                        writer.write(json!({
+                            "path": record.display_name().to_string_lossy(),
+                            "algorithm": record.algo.name(),
+                            "digest": encoding::encode(&record.hash, record.algo, flags.encoding),
+                            "size": record.size,
+                            "mode": if record.binary { "binary" } else { "text" },
+                            "status": "ok",
+                            "error": null,
+                        }))?;
+                        return Ok(());
+                    }
+                    // If zero the names are not escaped, don't print EOL and add NUL
+                    let mut output =
+                        format_output_line(&record, flags.tag, !flags.zero, flags.encoding);
+                    output.push(if flags.zero { b'\0' } else { b'\n' });
+                    handle.write_all(&output)?;
+                    // The lines are shown as the files are hashed with the progress
+                    if progress.is_some() {
+                        handle.flush()?;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Couldn't open file {}: {}", paths[idx].display(), e);
+                    error_counter += 1;
+                    if let Some(writer) = writer.as_mut() {
+                        writer.write(json!({
+                            "path": names[idx].to_string_lossy(),
+                            "algorithm": flags.algorithm.name(),
+                            "digest": null,
+                            "size": null,
+                            "mode": if flags.binary { "binary" } else { "text" },
+                            "status": "error",
+                            "error": e,
+                        }))?;
+                    }
+                }
+            }
+            Ok(())
+        },
+    )?;
+    handle.flush()?;
+    if let Some(writer) = writer {
+        writer.finish(None)?;
+    }
+
+    if error_counter == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} errors detected", error_counter).into())
+    }
+}
+
+/// Returns the records of a checksum file, or hashes the files of a
+/// directory with algorithm, named relative to the directory
+fn load_manifest(
+    file_name: &OsStr,
+    algorithm: Algorithm,
+    flags: &CommandLineFlags,
+    cache: Option<&Cache>,
+) -> Result<Vec<ChecksumRecord>, Box<dyn Error>> {
+    if !Path::new(file_name).is_dir() {
+        let warn = flags.check_output == CheckOutput::Warn;
+        return Ok(parse_check_file(file_name, flags.algorithm, warn)?.0);
+    }
+
+    let default_options = WalkOptions::default();
+    let options = flags.recursive.as_ref().unwrap_or(&default_options);
+    let (files, mut error_counter) = walk::walk(&[file_name.to_owned()], options)?;
+    let (paths, names): (Vec<_>, Vec<_>) = files.into_iter().unzip();
+    let mut records = vec![];
+    hash_files(&paths, algorithm, flags, cache, None, |idx, record| {
+        match record {
+            Ok(mut record) => {
+                record.file_name = names[idx].clone();
+                records.push(record);
+            }
+            Err(e) => {
+                eprintln!("Couldn't open file {}: {}", paths[idx].display(), e);
+                error_counter += 1;
+            }
+        }
+        Ok(())
+    })?;
+
+    if error_counter == 0 {
+        Ok(records)
+    } else {
+        Err(format!("{} errors detected", error_counter).into())
+    }
+}
+
+/// Prints the changes from the old manifest to the new one, each one can
+/// be a checksum file or a directory. The directories are hashed with the
+/// algorithm of the checksum file they are compared to. As diff, the exit
+/// status fails if there is any change.
+fn diff(
+    old: &OsStr,
+    new: &OsStr,
+    flags: &CommandLineFlags,
+    cache: Option<&Cache>,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let (old_records, new_records) = if Path::new(old).is_dir() {
+        let new_records = load_manifest(new, flags.algorithm, flags, cache)?;
+        let algorithm = new_records.first().map_or(flags.algorithm, |r| r.algo);
+        (load_manifest(old, algorithm, flags, cache)?, new_records)
+    } else {
+        let old_records = load_manifest(old, flags.algorithm, flags, cache)?;
+        let algorithm = old_records.first().map_or(flags.algorithm, |r| r.algo);
+        (old_records, load_manifest(new, algorithm, flags, cache)?)
+    };
+
+    let changes = diff::compare(&old_records, &new_records);
+    match flags.format {
+        OutputFormat::Json => println!("{}", diff::to_json(&changes)),
+        _ => {
+            for change in changes.iter() {
+                println!("{}", change.to_text());
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// Finds the files with the same contents in the input files and the
+/// directories walked, prints the groups found and takes the action
+/// selected on the duplicates. The empty files are skipped, and the hard
+/// links to the same file are listed once.
+fn find_duplicates(
+    input_files: &[OsString],
+    flags: &CommandLineFlags,
+    cache: Option<&Cache>,
+) -> Result<(), Box<dyn Error>> {
+    if flags.algorithm.is_legacy() {
+        return Err("--find-duplicates is not supported with --algorithm={bsd,sysv,crc}".into());
+    }
+    if input_files.iter().any(|file_name| file_name == "-") {
+        return Err("--find-duplicates needs files or directories, not stdin".into());
+    }
+    let default_options = WalkOptions::default();
+    let options = flags.recursive.as_ref().unwrap_or(&default_options);
+    let (files, mut error_counter) = walk::walk(input_files, options)?;
+    let paths = files.into_iter().map(|(path, _)| path).collect::<Vec<_>>();
+
+    let mut inodes = HashSet::new();
+    let groups = dupes::split_groups(vec![paths], |path| match fs::metadata(path) {
+        Ok(metadata) if metadata.len() == 0 => None,
+        Ok(metadata) => inodes
+            .insert((metadata.dev(), metadata.ino()))
+            .then_some(metadata.len()),
+        Err(e) => {
+            eprintln!("Couldn't open file {}: {}", path.display(), e);
+            error_counter += 1;
+            None
+        }
+    });
+    let groups = dupes::split_groups(groups, |path| {
+        match dupes::partial_digest(Path::new(path), flags.algorithm) {
+            Ok(digest) => Some(digest),
+            Err(e) => {
+                eprintln!("Couldn't open file {}: {}", path.display(), e);
+                error_counter += 1;
+                None
+            }
+        }
+    });
+
+    // Only the files left in a group are hashed completely
+    let candidates = groups.concat();
+    let mut records = HashMap::new();
+    hash_files(
+        &candidates,
+        flags.algorithm,
+        flags,
+        cache,
+        None,
+        |idx, record| {
+            match record {
+                Ok(record) => {
+                    records.insert(candidates[idx].clone(), record);
+                }
+                Err(e) => {
+                    eprintln!("Couldn't open file {}: {}", candidates[idx].display(), e);
+                    error_counter += 1;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    let groups = dupes::split_groups(groups, |path| {
+        records
+            .get(path)
+            .map(|record| record.hash.to_ascii_lowercase())
+    })
+    .into_iter()
+    .map(|files| DuplicateGroup {
+        digest: records[&files[0]].hash.clone(),
+        size: records[&files[0]].size,
+        files,
+    })
+    .collect::<Vec<_>>();
+
+    match flags.format {
+        OutputFormat::Json => println!("{}", dupes::to_json(&groups, flags.algorithm)),
+        _ => {
+            // Each group is a list of checksum lines, separated by empty lines
+            let end = if flags.zero { b'\0' } else { b'\n' };
+            let mut handle = io::stdout().lock();
+            for (idx, group) in groups.iter().enumerate() {
+                if idx > 0 {
+                    handle.write_all(&[end])?;
+                }
+                for file_name in group.files.iter() {
+                    let mut output = format_output_line(
+                        &records[file_name],
+                        flags.tag,
+                        !flags.zero,
+                        flags.encoding,
+                    );
+                    output.push(end);
+                    handle.write_all(&output)?;
+                }
+            }
+        }
+    }
+
+    if let Some(action) = flags.duplicate_action {
+        for group in groups.iter() {
+            let keep = Path::new(&group.files[0]);
+            for file_name in group.files[1..].iter().map(Path::new) {
+                // The messages of the dry run, the action done and the error
+                let (messages, target, result) = match action {
+                    DuplicateAction::Hardlink => (
+                        ["Would link", "Linked", "Couldn't link"],
+                        format!("{} to {}", file_name.display(), keep.display()),
+                        (!flags.dry_run).then(|| dupes::hardlink(keep, file_name)),
+                    ),
+                    DuplicateAction::DeleteKeepFirst => (
+                        ["Would delete", "Deleted", "Couldn't delete"],
+                        file_name.display().to_string(),
+                        (!flags.dry_run).then(|| fs::remove_file(file_name)),
+                    ),
+                };
+                match result {
+                    None => eprintln!("{} {}", messages[0], target),
+                    Some(Ok(())) => eprintln!("{} {}", messages[1], target),
+                    Some(Err(e)) => {
+                        eprintln!("{} {}: {}", messages[2], target, e);
+                        error_counter += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    if error_counter == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} errors detected", error_counter).into())
+    }
+}
+
+/// Entry point of the checksum tools, bin_name is the name of the tool
+/// and algorithm the digest used unless another is selected with -a
+/// Returns the exit status of the tool or the error that stopped it
+pub fn run(bin_name: &'static str, algorithm: Algorithm) -> Result<ExitCode, Box<dyn Error>> {
+    let cmd_line = parse_input_args(bin_name, algorithm);
+    let input_files = cmd_line.0;
+    let flags = cmd_line.1;
+    let cache = match &flags.cache {
+        Some(path) => Some(Cache::open(path, flags.cache_verify)?),
+        None => None,
+    };
+
+    let records_format = matches!(flags.format, OutputFormat::Csv | OutputFormat::Ndjson);
+    let result = if (flags.diff || flags.find_duplicates) && records_format {
+        Err("--diff and --find-duplicates only support --format=text or json".into())
+    } else if flags.format == OutputFormat::Groups && !flags.find_duplicates {
+        Err("--format=groups needs --find-duplicates".into())
+    } else if flags.encoding != Encoding::Hex && flags.algorithm.is_legacy() && !flags.check {
+        // The legacy checksums are decimal numbers
+        Err("--encoding is not supported with --algorithm={bsd,sysv,crc}".into())
+    } else if flags.diff {
+        let [old, new] = input_files.as_slice() else {
+            return Err("--diff needs the old and the new checksum file or directory".into());
+        };
+        diff(old, new, &flags, cache.as_ref())
+    } else if flags.find_duplicates {
+        find_duplicates(&input_files, &flags, cache.as_ref()).map(|_| ExitCode::SUCCESS)
+    } else if !flags.check {
+        print_output(&input_files, &flags, cache.as_ref()).map(|_| ExitCode::SUCCESS)
+    } else if flags.algorithm.is_legacy() && flags.algorithm_selected {
+        // Without -a cksum takes the algorithm of each line of the check file
+        Err("--check is not supported with --algorithm={bsd,sysv,crc}".into())
+    } else {
+        // Every check file is verified, the result fails if any of them fails
+        let mut writer = match flags.check_output {
+            CheckOutput::Status => None,
+            _ => RecordWriter::new(flags.format, records::CHECK_FIELDS, io::stdout())?,
+        };
+        let mut report = flags
+            .report
+            .as_ref()
+            .map(|(format, _)| Report::new(*format));
+        let mut success = true;
+        let mut total = CheckSummary::default();
+        for file_name in input_files.iter() {
+            match check(
+                file_name,
+                &flags,
+                cache.as_ref(),
+                writer.as_mut(),
+                report.as_mut(),
+            ) {
+                Ok(summary) => {
+                    success &= summary.is_success(flags.strict);
+                    total.merge(&summary);
+                }
+                Err(e) => {
+                    eprintln!("Couldn't check file {}: {}", file_name.display(), e);
+                    success = false;
+                    // The check file is a single failed test case
+                    if let Some(report) = report.as_mut() {
+                        let name = file_name.to_string_lossy().into_owned();
+                        let case = TestCase {
+                            name: name.clone(),
+                            failure: Some(Failure {
+                                kind: "manifest-error",
+                                message: e.to_string(),
+                                expected: None,
+                                actual: None,
+                            }),
+                        };
+                        report.add_suite(name, vec![case]);
+                    }
+                }
+            }
+        }
+        if let (Some(report), Some((_, path))) = (report, &flags.report) {
+            fs::write(path, report.render())?;
+        }
+        if let Some(writer) = writer {
+            writer.finish(Some(total.to_json(success)))?;
+        }
+        if success {
+            Ok(ExitCode::SUCCESS)
+        } else {
+            Ok(ExitCode::FAILURE)
+        }
+    };
+
+    // The digests computed are stored even if some files failed
+    if let Some(cache) = cache {
+        cache.save()?;
+        let mismatches = cache.mismatches();
+        if mismatches > 0 && result.is_ok() {
+            return Err(format!("{} cached digests didn't match the files", mismatches).into());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod md5sum_test {
+
+    use super::*;
+
+    #[test]
+    fn format_output_line_non_bsd() {
+        let file_name = "filename";
+        let hash = "4e7bb796c99cf98ae40b32b644119c74";
+        let mut record = ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: file_name.into(),
+            binary: false,
+            hash: hash.to_owned(),
+            size: 0,
+            range: None,
+        };
+        let test1 = format_output_line(&record, false, true, Encoding::Hex);
+        assert_eq!(test1, format!("{}  {}", hash, file_name).into_bytes());
+
+        record.binary = true;
+        let test2 = format_output_line(&record, false, true, Encoding::Hex);
+        assert_eq!(test2, format!("{} *{}", hash, file_name).into_bytes());
+    }
+
+    #[test]
+    fn format_output_line_bsd() {
+        let file_name = "filename";
+        let hash = "4e7bb796c99cf98ae40b32b644119c74";
+        let mut record = ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: file_name.into(),
+            binary: false,
+            hash: hash.to_owned(),
+            size: 0,
+            range: None,
+        };
+        let test1 = format_output_line(&record, true, true, Encoding::Hex);
+        assert_eq!(
+            test1,
+            format!("MD5 ({}) = {}", file_name, hash).into_bytes()
+        );
+
+        record.binary = true;
+        let test2 = format_output_line(&record, true, true, Encoding::Hex);
+        assert_eq!(
+            test2,
+            format!("MD5 ({}) = {}", file_name, hash).into_bytes()
+        );
+    }
+
+    #[test]
+    fn parse_line_correct_line() {
+        let line_1 = "4e7bb796c99cf98ae40b32b644119c74  src/main.rs";
+        let line_2 = "4e7bb796c99cf98ae40b32b644119c74 *src/main.rs";
+
+        let output_1 = parse_line(line_1.as_bytes(), Algorithm::Md5).unwrap();
+        let output_2 = parse_line(line_2.as_bytes(), Algorithm::Md5).unwrap();
+
+        assert_eq!("4e7bb796c99cf98ae40b32b644119c74", output_1.hash);
+        assert_eq!("4e7bb796c99cf98ae40b32b644119c74", output_2.hash);
+
+        assert!(!output_1.binary);
+        assert!(output_2.binary);
+
+        assert_eq!("src/main.rs", output_1.file_name);
+        assert_eq!("src/main.rs", output_2.file_name);
+    }
+
+    #[test]
+    fn parse_line_return_error() {
+        let line_1 = "4e9cf98ae40b32b644119c74  src/main.rs"; // hash is too short
+        let line_2 = "4e7bb796c99cf98ae40b32b644119c74 &src/main.rs"; // wrong binary symbol
+        let line_3 = "4e7bb796c99cf98ae40b32b644119c74   src/main.rs"; // too many spaces
+        let line_4 = "4e7bb796c99cf98ae40b32b644119c74  *src/main.rs"; // two spaces and *
+        let line_5 = "4e7bb796c99cf98ae40b32b644119c74   "; // missing file name
+
+        assert!(parse_line(line_1.as_bytes(), Algorithm::Md5).is_err());
+        assert!(parse_line(line_2.as_bytes(), Algorithm::Md5).is_err());
+        assert!(parse_line(line_3.as_bytes(), Algorithm::Md5).is_err());
+        assert!(parse_line(line_4.as_bytes(), Algorithm::Md5).is_err());
+        assert!(parse_line(line_5.as_bytes(), Algorithm::Md5).is_err());
+    }
+
+    #[test]
+    fn parse_line_bsd_style() {
+        let line = "MD5 (src/main.rs) = 4e7bb796c99cf98ae40b32b644119c74";
+        let output = parse_line(line.as_bytes(), Algorithm::Md5).unwrap();
+        assert_eq!(Algorithm::Md5, output.algo);
+        assert_eq!("src/main.rs", output.file_name);
+        assert_eq!("4e7bb796c99cf98ae40b32b644119c74", output.hash);
+        assert!(output.binary);
+
+        // The tag selects the algorithm, not the default one
+        let line = "SHA1 (a (copy).txt) = a9993e364706816aba3e25717850c26c9cd0d89d";
+        let output = parse_line(line.as_bytes(), Algorithm::Md5).unwrap();
+        assert_eq!(Algorithm::Sha1, output.algo);
+        assert_eq!("a (copy).txt", output.file_name);
+
+        let unknown_tag = "MD4 (src/main.rs) = 4e7bb796c99cf98ae40b32b644119c74";
+        let wrong_length = "SHA1 (src/main.rs) = 4e7bb796c99cf98ae40b32b644119c74";
+        let legacy_tag = "CRC (src/main.rs) = 48aa78a2";
+        assert!(parse_line(unknown_tag.as_bytes(), Algorithm::Md5).is_err());
+        assert!(parse_line(wrong_length.as_bytes(), Algorithm::Md5).is_err());
+        assert!(parse_line(legacy_tag.as_bytes(), Algorithm::Crc).is_err());
+    }
+
+    #[test]
+    fn parse_line_dispatch_on_length() {
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let line = format!("{}  abc.txt", sha256);
+
+        assert_eq!(
+            Algorithm::Sha256,
+            parse_line(line.as_bytes(), Algorithm::Md5).unwrap().algo
+        );
+        assert_eq!(
+            Algorithm::Sha256,
+            parse_line(line.as_bytes(), Algorithm::Crc).unwrap().algo
+        );
+        // The default algorithm wins when several have the same length
+        assert_eq!(
+            Algorithm::Sm3,
+            parse_line(line.as_bytes(), Algorithm::Sm3).unwrap().algo
+        );
+    }
+
+    #[test]
+    fn parse_check_file_mixed_formats() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.path().join("mixed.txt");
+        fs::write(
+            &path,
+            "# checksums of the release\r\n\
+             MD5 (first) = 4e7bb796c99cf98ae40b32b644119c74\r\n\
+             \r\n\
+             4e7bb796c99cf98ae40b32b644119c74 *second\r\n\
+             a9993e364706816aba3e25717850c26c9cd0d89d  third\r",
+        )
+        .unwrap();
+        let (records, malformed) =
+            parse_check_file(path.as_os_str(), Algorithm::Md5, false).unwrap();
+
+        let names = records
+            .iter()
+            .map(|r| r.file_name.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["first", "second", "third"], names);
+        assert_eq!(Algorithm::Sha1, records[2].algo);
+        assert!(malformed.is_empty());
+    }
+
+    #[test]
+    fn check_summary_success() {
+        let mut summary = CheckSummary {
+            verified: 2,
+            ..Default::default()
+        };
+        assert!(summary.is_success(true));
+
+        summary.malformed = 1;
+        assert!(summary.is_success(false));
+        assert!(!summary.is_success(true));
+
+        summary.mismatched = 1;
+        assert!(!summary.is_success(false));
+
+        let summary = CheckSummary {
+            unreadable: 1,
+            verified: 1,
+            ..Default::default()
+        };
+        assert!(!summary.is_success(false));
+
+        // Every file was missing with --ignore-missing
+        assert!(!CheckSummary::default().is_success(false));
+    }
+
+    #[test]
+    fn resolve_listed_file_paths() {
+        let resolve = |check_file: &str, listed: &str, relative_to_manifest: bool| {
+            resolve_listed_file(
+                OsStr::new(check_file),
+                OsStr::new(listed),
+                relative_to_manifest,
+            )
+        };
+        assert_eq!("a.txt", resolve("dir/check.md5", "a.txt", false));
+        assert_eq!("dir/a.txt", resolve("dir/check.md5", "a.txt", true));
+        assert_eq!("a.txt", resolve("check.md5", "a.txt", true));
+        assert_eq!("/tmp/a.txt", resolve("dir/check.md5", "/tmp/a.txt", true));
+        assert_eq!("a.txt", resolve("-", "a.txt", true));
+        assert_eq!("-", resolve("dir/check.md5", "-", true));
+    }
+
+    #[test]
+    fn verify_record_compares_digests() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.path().join("verify.txt");
+        fs::write(&path, "abc").unwrap();
+        let mut record = ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: path.clone().into_os_string(),
+            binary: true,
+            hash: "900150983CD24FB0D6963F7D28E17F72".to_owned(),
+            size: 0,
+            range: None,
+        };
+        assert_eq!(
+            CheckResult::Ok,
+            verify_record(&record, DEFAULT_BUFFER_SIZE, None, None)
+        );
+        record.binary = false;
+        assert_eq!(
+            CheckResult::Ok,
+            verify_record(&record, DEFAULT_BUFFER_SIZE, None, None)
+        );
+
+        record.hash = "d41d8cd98f00b204e9800998ecf8427e".to_owned();
+        assert_eq!(
+            CheckResult::Mismatch("900150983cd24fb0d6963f7d28e17f72".to_owned()),
+            verify_record(&record, DEFAULT_BUFFER_SIZE, None, None)
+        );
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            verify_record(&record, DEFAULT_BUFFER_SIZE, None, None),
+            CheckResult::Missing(_)
+        ));
+
+        record.file_name = temp.path().as_os_str().to_owned();
+        assert!(matches!(
+            verify_record(&record, DEFAULT_BUFFER_SIZE, None, None),
+            CheckResult::ReadError(_)
+        ));
+    }
+
+    #[test]
+    fn escaped_names_round_trip() {
+        let every_byte = (1..=255).collect::<Vec<u8>>();
+        let names: [&[u8]; 8] = [
+            b"x@1+2",
+            b"back\\slash@0+3",
+            b"new\nline",
+            b"back\\slash",
+            b"carriage\rreturn",
+            b"\\n",
+            b"not utf-8 \xff\xfe",
+            &every_byte,
+        ];
+        for name in names {
+            for (tag, binary) in [(false, false), (false, true), (true, true)] {
+                let record = ChecksumRecord {
+                    algo: Algorithm::Md5,
+                    file_name: OsStr::from_bytes(name).to_owned(),
+                    binary,
+                    hash: "900150983cd24fb0d6963f7d28e17f72".to_owned(),
+                    size: 0,
+                    range: None,
+                };
+                let line = format_output_line(&record, tag, true, Encoding::Hex);
+                assert!(!line.contains(&b'\n') && !line.contains(&b'\r'));
+                assert_eq!(record, parse_line(&line, Algorithm::Md5).unwrap());
+            }
+        }
+
+        // The names are written as they are without escaping
+        let record = ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: OsStr::from_bytes(b"a\\b\n").to_owned(),
+            binary: false,
+            hash: "900150983cd24fb0d6963f7d28e17f72".to_owned(),
+            size: 0,
+            range: None,
+        };
+        assert_eq!(
+            b"900150983cd24fb0d6963f7d28e17f72  a\\b\n".to_vec(),
+            format_output_line(&record, false, false, Encoding::Hex)
+        );
+        assert_eq!(
+            b"\\900150983cd24fb0d6963f7d28e17f72  a\\\\b\\n".to_vec(),
+            format_output_line(&record, false, true, Encoding::Hex)
+        );
+        assert!(parse_line(b"\\900150983cd24fb0d6963f7d28e17f72  a\\tb", Algorithm::Md5).is_err());
+
+        // The @ of a whole file named as a range is escaped
+        let record = ChecksumRecord {
+            file_name: OsString::from("x@1+2"),
+            ..record
+        };
+        assert_eq!(
+            b"\\900150983cd24fb0d6963f7d28e17f72  x\\@1+2".to_vec(),
+            format_output_line(&record, false, true, Encoding::Hex)
+        );
+        let record = ChecksumRecord {
+            range: Some(ByteRange {
+                offset: 0,
+                length: 3,
+            }),
+            ..record
+        };
+        assert_eq!(
+            b"900150983cd24fb0d6963f7d28e17f72  x@1+2@0+3".to_vec(),
+            format_output_line(&record, false, true, Encoding::Hex)
+        );
+    }
+
+    #[test]
+    fn compare_checksum_records() {
+        let record_a = ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: "one_file".into(),
+            binary: true,
+            hash: "4e7bb796c99cf98ae40b32b644119c74".to_owned(),
+            size: 0,
+            range: None,
+        };
+        let mut record_b = ChecksumRecord {
+            algo: Algorithm::Md5,
+            file_name: "one_file".into(),
+            binary: true,
+            hash: "4e7bb796c99cf98ae40b32b644119c74".to_owned(),
+            size: 0,
+            range: None,
+        };
+        assert_eq!(record_a, record_b);
+
+        record_b.file_name = "other_name".into();
+        assert_ne!(record_a, record_b);
+
+        record_b.file_name = "one_file".into();
+        assert_eq!(record_a, record_b);
+        record_b.binary = false;
+        assert_ne!(record_a, record_b);
+
+        record_b.binary = true;
+        assert_eq!(record_a, record_b);
+        record_b.hash = "5e7bb796c99cf98ae40b32b644119c74".to_owned();
+        assert_ne!(record_a, record_b);
+    }
+
+    #[test]
+    fn hash_reader_any_buffer_size() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+        for buffer_size in [1, 7, 64, 4096] {
+            let (digest, size) =
+                hash_reader(&mut &data[..], Algorithm::Md5.new_digest(), buffer_size).unwrap();
+            assert_eq!("9e107d9d372bb6826bd81d3542a419d6", to_hex(&digest));
+            assert_eq!(data.len() as u64, size);
+        }
+
+        let (digest, _) = hash_reader(
+            &mut io::empty(),
+            Algorithm::Md5.new_digest(),
+            DEFAULT_BUFFER_SIZE,
+        )
+        .unwrap();
+        assert_eq!("d41d8cd98f00b204e9800998ecf8427e", to_hex(&digest));
+    }
+
+    #[test]
+    fn format_output_line_legacy() {
+        let mut record = ChecksumRecord {
+            algo: Algorithm::Crc,
+            file_name: "filename".into(),
+            binary: false,
+            hash: "48aa78a2".to_owned(),
+            size: 3,
+            range: None,
+        };
+        assert_eq!(
+            "1219131554 3 filename".as_bytes(),
+            format_output_line(&record, true, true, Encoding::Hex)
+        );
+        record.file_name = "-".into();
+        assert_eq!(
+            "1219131554 3".as_bytes(),
+            format_output_line(&record, false, true, Encoding::Hex)
+        );
+
+        record.algo = Algorithm::Bsd;
+        record.hash = "40ac".to_owned();
+        record.size = 1025;
+        assert_eq!(
+            "16556     2".as_bytes(),
+            format_output_line(&record, false, true, Encoding::Hex)
+        );
+
+        record.algo = Algorithm::Sysv;
+        record.hash = "0126".to_owned();
+        record.file_name = "filename".into();
+        assert_eq!(
+            "294 3 filename".as_bytes(),
+            format_output_line(&record, false, true, Encoding::Hex)
+        );
+    }
+}
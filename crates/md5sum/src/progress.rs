@@ -0,0 +1,248 @@
+//! Progress of the hashing on stderr. A status line with the bytes hashed,
+//! the throughput, the estimated time left and the current file is redrawn
+//! periodically. The line is cleared before any output line is written, so
+//! they never share a line of the terminal.
+
+use crate::range::RangeSpec;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time between the updates of the status line
+const INTERVAL: Duration = Duration::from_millis(200);
+
+/// Maximum width of the status line, so it never wraps on a terminal of 80 columns
+const MAX_WIDTH: usize = 79;
+
+/// Counters of the files and bytes hashed, shared by the hashing threads
+#[derive(Debug)]
+pub struct Progress {
+    total_files: usize,
+    /// Bytes to hash in the files found, the size of stdin is unknown
+    total_bytes: u64,
+    files: AtomicUsize,
+    bytes: AtomicU64,
+    current: Mutex<OsString>,
+    start: Instant,
+    /// Held while the status line or an output line is written, true while
+    /// the status line is shown
+    terminal: Mutex<bool>,
+}
+
+impl Progress {
+    /// Returns the progress of the files and the ranges hashed in them, the
+    /// total is the length of the ranges or the size of the whole files.
+    /// The progress is only shown on a terminal, so the files are only
+    /// stat'ed when stderr is one.
+    pub fn new<'a>(files: impl Iterator<Item = (&'a OsStr, Option<RangeSpec>)>) -> Progress {
+        let mut total_files = 0;
+        let mut total_bytes = 0;
+        for (file_name, range) in files {
+            total_files += 1;
+            if file_name != "-"
+                && let Ok(metadata) = fs::metadata(file_name)
+            {
+                total_bytes +=
+                    range.map_or(metadata.len(), |range| range.length_in(metadata.len()));
+            }
+        }
+        Progress {
+            total_files,
+            total_bytes,
+            files: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            current: Mutex::new(OsString::new()),
+            start: Instant::now(),
+            terminal: Mutex::new(false),
+        }
+    }
+
+    pub fn start_file(&self, file_name: &OsStr) {
+        *self.current.lock().unwrap() = file_name.to_owned();
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn finish_file(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Clears the status line until the returned guard is dropped, the
+    /// output written meanwhile starts at the beginning of a line
+    pub fn hide(&self) -> MutexGuard<'_, bool> {
+        let mut shown = self.terminal.lock().unwrap();
+        if *shown {
+            let _ = io::stderr().write_all(b"\r\x1b[K");
+            *shown = false;
+        }
+        shown
+    }
+
+    fn draw(&self) {
+        let line = self.status_line(self.start.elapsed());
+        let mut shown = self.terminal.lock().unwrap();
+        // The output lines are flushed before the status line is drawn
+        let _ = io::stdout().flush();
+        let _ = write!(io::stderr(), "\r\x1b[K{}", line);
+        *shown = true;
+    }
+
+    /// Returns the status line after elapsed time, the file count is only
+    /// shown with several files
+    fn status_line(&self, elapsed: Duration) -> String {
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let rate = bytes as f64 / elapsed.as_secs_f64().max(0.001);
+        let mut line = String::new();
+        if self.total_files > 1 {
+            let files = self.files.load(Ordering::Relaxed);
+            line.push_str(&format!("[{}/{} files] ", files, self.total_files));
+        }
+        line.push_str(&format_bytes(bytes));
+        if self.total_bytes >= bytes && self.total_bytes > 0 {
+            let percent = bytes as f64 * 100.0 / self.total_bytes as f64;
+            let eta = if rate > 0.0 {
+                format_duration((self.total_bytes - bytes) as f64 / rate)
+            } else {
+                "--:--".to_owned()
+            };
+            line.push_str(&format!(
+                " / {} ({:.0}%) {}/s ETA {}",
+                format_bytes(self.total_bytes),
+                percent,
+                format_bytes(rate as u64),
+                eta
+            ));
+        } else {
+            line.push_str(&format!(" {}/s", format_bytes(rate as u64)));
+        }
+
+        // The end of the file name is kept if the line is too long
+        let current = self.current.lock().unwrap().to_string_lossy().into_owned();
+        let room = MAX_WIDTH.saturating_sub(line.chars().count() + 1);
+        let length = current.chars().count();
+        if room > 3 && length > room {
+            let tail = current.chars().skip(length - room + 3).collect::<String>();
+            line.push_str(&format!(" ...{}", tail));
+        } else if length <= room {
+            line.push(' ');
+            line.push_str(&current);
+        }
+        line
+    }
+}
+
+/// Reader that adds the bytes read to the progress, if any
+pub struct ProgressReader<'a, R> {
+    inner: R,
+    progress: Option<&'a Progress>,
+}
+
+impl<'a, R: Read> ProgressReader<'a, R> {
+    pub fn new(inner: R, progress: Option<&'a Progress>) -> ProgressReader<'a, R> {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buffer)?;
+        if let Some(progress) = self.progress {
+            progress.add_bytes(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+/// Runs f while the status line is redrawn periodically, and clears it
+/// when f finishes. Without progress f just runs.
+pub fn run<T>(progress: Option<&Progress>, f: impl FnOnce() -> T) -> T {
+    let Some(progress) = progress else {
+        return f();
+    };
+    let (stop, stopped) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(INTERVAL) {
+                progress.draw();
+            }
+            drop(progress.hide());
+        });
+        let result = f();
+        drop(stop);
+        result
+    })
+}
+
+/// Returns the size with a binary unit
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Returns the seconds as minutes and seconds, with the hours if needed
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use super::*;
+
+    #[test]
+    fn format_units() {
+        assert_eq!("1023 B", format_bytes(1023));
+        assert_eq!("1.5 KiB", format_bytes(1536));
+        assert_eq!("100.0 GiB", format_bytes(100 << 30));
+        assert_eq!("0:59", format_duration(59.4));
+        assert_eq!("1:02:03", format_duration(3723.0));
+    }
+
+    #[test]
+    fn status_lines() {
+        let progress = Progress {
+            total_files: 1,
+            total_bytes: 4 << 30,
+            files: AtomicUsize::new(0),
+            bytes: AtomicU64::new(1 << 30),
+            current: Mutex::new("disk.img".into()),
+            start: Instant::now(),
+            terminal: Mutex::new(false),
+        };
+        assert_eq!(
+            "1.0 GiB / 4.0 GiB (25%) 102.4 MiB/s ETA 0:30 disk.img",
+            progress.status_line(Duration::from_secs(10))
+        );
+
+        let progress = Progress {
+            total_files: 10,
+            files: AtomicUsize::new(3),
+            current: Mutex::new(format!("{}/file.bin", "dir".repeat(30)).into()),
+            ..progress
+        };
+        let line = progress.status_line(Duration::from_secs(10));
+        assert!(line.starts_with("[3/10 files] 1.0 GiB / 4.0 GiB (25%)"));
+        assert!(line.ends_with("dirdir/file.bin") && line.contains(" ..."));
+        assert_eq!(MAX_WIDTH, line.chars().count());
+    }
+}
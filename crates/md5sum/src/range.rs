@@ -0,0 +1,229 @@
+//! Byte ranges of the files. Only a slice of the file is hashed, and the
+//! checksum lines name it as `FILE@OFFSET+LENGTH`.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Slice of the files selected in the command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeSpec {
+    /// From offset to the end of the file, or length bytes
+    Slice { offset: u64, length: Option<u64> },
+    /// The last bytes of the file
+    Tail(u64),
+}
+
+impl RangeSpec {
+    /// Returns the offset and the length of the range in a file of size
+    /// bytes, the size of stdin is unknown
+    fn resolve(&self, size: Option<u64>) -> io::Result<(u64, Option<u64>)> {
+        match (*self, size) {
+            (RangeSpec::Slice { offset, length }, _) => Ok((offset, length)),
+            (RangeSpec::Tail(length), Some(size)) => {
+                Ok((size.saturating_sub(length), Some(length)))
+            }
+            (RangeSpec::Tail(_), None) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the end of the input is unknown, --tail needs a file",
+            )),
+        }
+    }
+
+    /// Returns the number of bytes of the range in a file of size bytes
+    pub fn length_in(&self, size: u64) -> u64 {
+        match *self {
+            RangeSpec::Slice { offset, length } => {
+                let rest = size.saturating_sub(offset);
+                length.map_or(rest, |length| length.min(rest))
+            }
+            RangeSpec::Tail(length) => length.min(size),
+        }
+    }
+}
+
+/// Slice of a file hashed, as written in the checksum lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteRange {
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl ByteRange {
+    /// Returns the suffix of the file name, `@OFFSET+LENGTH`
+    pub fn suffix(&self) -> String {
+        format!("@{}+{}", self.offset, self.length)
+    }
+
+    /// Splits the range suffix of a file name, if it has one. The name is
+    /// split before unescaping it, an escaped `\@` is part of the name.
+    pub fn split_name(name: &[u8]) -> Option<(&[u8], ByteRange)> {
+        let at = name.iter().rposition(|&byte| byte == b'@')?;
+        let backslashes = name[..at].iter().rev().take_while(|&&byte| byte == b'\\');
+        if backslashes.count() % 2 == 1 {
+            return None;
+        }
+        let (offset, length) = std::str::from_utf8(&name[at + 1..]).ok()?.split_once('+')?;
+        let all_digits = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+        if at == 0 || !all_digits(offset) || !all_digits(length) {
+            return None;
+        }
+        let range = ByteRange {
+            offset: offset.parse().ok()?,
+            length: length.parse().ok()?,
+        };
+        Some((&name[..at], range))
+    }
+
+    /// Escapes the `@` of a name that would be split as a range, `x@1+2` is
+    /// written as `x\@1+2`. Returns whether the name was changed.
+    pub fn escape_name(name: &mut Vec<u8>) -> bool {
+        let Some(at) = ByteRange::split_name(name).map(|(prefix, _)| prefix.len()) else {
+            return false;
+        };
+        name.insert(at, b'\\');
+        true
+    }
+}
+
+impl From<ByteRange> for RangeSpec {
+    fn from(range: ByteRange) -> RangeSpec {
+        RangeSpec::Slice {
+            offset: range.offset,
+            length: Some(range.length),
+        }
+    }
+}
+
+/// Opens the file, or stdin for -, and returns a reader of the range and
+/// its offset. The files are read from the offset, the bytes of stdin
+/// before the offset are skipped.
+pub fn open(file_name: &OsStr, range: Option<&RangeSpec>) -> io::Result<(Box<dyn Read>, u64)> {
+    let Some(range) = range else {
+        let input: Box<dyn Read> = if file_name == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(File::open(file_name)?)
+        };
+        return Ok((input, 0));
+    };
+
+    let (input, offset, length): (Box<dyn Read>, _, _) = if file_name == "-" {
+        let (offset, length) = range.resolve(None)?;
+        let mut stdin = io::stdin().lock();
+        io::copy(&mut (&mut stdin).take(offset), &mut io::sink())?;
+        (Box::new(stdin), offset, length)
+    } else {
+        let mut file = File::open(file_name)?;
+        let (offset, length) = range.resolve(Some(file.metadata()?.len()))?;
+        file.seek(SeekFrom::Start(offset))?;
+        (Box::new(file), offset, length)
+    };
+    Ok((Box::new(input.take(length.unwrap_or(u64::MAX))), offset))
+}
+
+/// Parses a number of bytes with an optional binary suffix, as 64K, 1M or 1GiB
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let units = ["K", "M", "G", "T", "P", "E"];
+    let digits = text.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &text[digits.len()..];
+    let shift = match suffix.trim_end_matches("iB").trim_end_matches('B') {
+        "" => 0,
+        unit => match units.iter().position(|u| u.eq_ignore_ascii_case(unit)) {
+            Some(idx) => 10 * (idx as u32 + 1),
+            None => return Err(format!("unknown unit {suffix}")),
+        },
+    };
+    let value = digits
+        .parse::<u64>()
+        .map_err(|e| format!("invalid size {text}: {e}"))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size {text} is too big"))
+}
+
+#[cfg(test)]
+mod range_tests {
+    use super::*;
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(Ok(512), parse_size("512"));
+        assert_eq!(Ok(64 << 10), parse_size("64K"));
+        assert_eq!(Ok(64 << 20), parse_size("64M"));
+        assert_eq!(Ok(1 << 30), parse_size("1G"));
+        assert_eq!(Ok(1 << 30), parse_size("1GiB"));
+        assert_eq!(Ok(3 << 40), parse_size("3t"));
+        assert!(parse_size("1X").is_err());
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("16E").is_err());
+    }
+
+    #[test]
+    fn split_range_names() {
+        let range = ByteRange {
+            offset: 1073741824,
+            length: 67108864,
+        };
+        assert_eq!("@1073741824+67108864", range.suffix());
+        assert_eq!(
+            Some((&b"disk@home.img"[..], range)),
+            ByteRange::split_name(b"disk@home.img@1073741824+67108864")
+        );
+        assert_eq!(None, ByteRange::split_name(b"file"));
+        assert_eq!(None, ByteRange::split_name(b"user@host"));
+        assert_eq!(None, ByteRange::split_name(b"@1+2"));
+        assert_eq!(None, ByteRange::split_name(b"file@1+"));
+        assert_eq!(None, ByteRange::split_name(b"file@+1+2"));
+        assert_eq!(None, ByteRange::split_name(b"x\\@1+2"));
+        assert_eq!(
+            Some((
+                &b"x\\\\"[..],
+                ByteRange {
+                    offset: 1,
+                    length: 2
+                }
+            )),
+            ByteRange::split_name(b"x\\\\@1+2")
+        );
+
+        let mut name = b"x@1+2".to_vec();
+        assert!(ByteRange::escape_name(&mut name));
+        assert_eq!(b"x\\@1+2".to_vec(), name);
+        let mut name = b"user@host".to_vec();
+        assert!(!ByteRange::escape_name(&mut name));
+    }
+
+    #[test]
+    fn resolve_ranges() {
+        let tail = RangeSpec::Tail(10);
+        assert_eq!((90, Some(10)), tail.resolve(Some(100)).unwrap());
+        assert_eq!((0, Some(10)), tail.resolve(Some(4)).unwrap());
+        assert!(tail.resolve(None).is_err());
+
+        let slice = RangeSpec::from(ByteRange {
+            offset: 5,
+            length: 2,
+        });
+        assert_eq!((5, Some(2)), slice.resolve(None).unwrap());
+    }
+
+    #[test]
+    fn range_lengths() {
+        assert_eq!(10, RangeSpec::Tail(10).length_in(100));
+        assert_eq!(4, RangeSpec::Tail(10).length_in(4));
+        let head = RangeSpec::Slice {
+            offset: 0,
+            length: Some(64),
+        };
+        assert_eq!(64, head.length_in(100));
+        assert_eq!(30, head.length_in(30));
+        let offset = RangeSpec::Slice {
+            offset: 90,
+            length: None,
+        };
+        assert_eq!(10, offset.length_in(100));
+        assert_eq!(0, offset.length_in(50));
+    }
+}
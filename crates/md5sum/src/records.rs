@@ -0,0 +1,187 @@
+//! Machine readable output of the digests and of the check results. The
+//! records are written as a JSON document, as CSV with a header line or as
+//! one JSON object per line.
+
+use crate::OutputFormat;
+use serde_json::{Value, json};
+use std::io::{self, Write};
+
+/// Fields of the records of the digests, the columns of the CSV output.
+/// The digest and the size of the files that can't be read are null.
+pub const HASH_FIELDS: &[&str] = &[
+    "path",
+    "algorithm",
+    "digest",
+    "size",
+    "mode",
+    "status",
+    "error",
+];
+
+/// Fields of the records of the check results
+pub const CHECK_FIELDS: &[&str] = &["path", "expected", "actual", "status", "error"];
+
+/// Writer of the records in one of the machine readable formats
+#[derive(Debug)]
+pub struct RecordWriter<W: Write> {
+    output: W,
+    format: OutputFormat,
+    fields: &'static [&'static str],
+    /// The records of the JSON format, written by finish in a single document
+    records: Vec<Value>,
+}
+
+impl<W: Write> RecordWriter<W> {
+    /// Returns the writer of the format, or None for the text format.
+    /// The header line of the CSV format is written.
+    pub fn new(
+        format: OutputFormat,
+        fields: &'static [&'static str],
+        mut output: W,
+    ) -> io::Result<Option<RecordWriter<W>>> {
+        match format {
+            OutputFormat::Text | OutputFormat::Groups => return Ok(None),
+            OutputFormat::Csv => writeln!(output, "{}", fields.join(","))?,
+            OutputFormat::Json | OutputFormat::Ndjson => {}
+        }
+        Ok(Some(RecordWriter {
+            output,
+            format,
+            fields,
+            records: vec![],
+        }))
+    }
+
+    /// Writes a record, a JSON object with the fields of the writer
+    pub fn write(&mut self, record: Value) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Csv => {
+                let row = self
+                    .fields
+                    .iter()
+                    .map(|field| csv_field(&record[field]))
+                    .collect::<Vec<_>>();
+                writeln!(self.output, "{}", row.join(","))
+            }
+            OutputFormat::Ndjson => writeln!(self.output, "{}", record),
+            OutputFormat::Text | OutputFormat::Groups | OutputFormat::Json => {
+                self.records.push(record);
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the records kept and the summary, if any. The JSON document is
+    /// the list of records, or an object with the records in files and the
+    /// summary. The NDJSON summary is the last line. The CSV summary is the
+    /// last row, with summary in the status column and the `name=value`
+    /// pairs of the summary in the error column.
+    pub fn finish(mut self, summary: Option<Value>) -> io::Result<()> {
+        match (self.format, summary) {
+            (OutputFormat::Json, None) => writeln!(self.output, "{}", Value::from(self.records)),
+            (OutputFormat::Json, Some(summary)) => writeln!(
+                self.output,
+                "{}",
+                json!({"files": self.records, "summary": summary})
+            ),
+            (OutputFormat::Ndjson, Some(summary)) => {
+                writeln!(self.output, "{}", json!({ "summary": summary }))
+            }
+            (OutputFormat::Csv, Some(Value::Object(summary))) => {
+                let pairs = summary
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>();
+                let row = json!({"status": "summary", "error": pairs.join(" ")});
+                self.write(row)
+            }
+            _ => Ok(()),
+        }?;
+        self.output.flush()
+    }
+}
+
+/// Returns the value as a CSV field, quoted if it has a comma, a quote
+/// or a line break. Null is an empty field.
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod records_tests {
+    use super::*;
+
+    fn output(format: OutputFormat, summary: Option<Value>) -> String {
+        let mut buffer = vec![];
+        let mut writer = RecordWriter::new(format, HASH_FIELDS, &mut buffer)
+            .unwrap()
+            .unwrap();
+        writer
+            .write(json!({"path": "a,\"b\"", "algorithm": "md5", "digest": "00", "size": 3, "mode": "text", "status": "ok", "error": null}))
+            .unwrap();
+        writer
+            .write(json!({"path": "c", "algorithm": "md5", "digest": null, "size": null, "mode": "binary", "status": "error", "error": "No such file or directory"}))
+            .unwrap();
+        writer.finish(summary).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn csv_records() {
+        assert_eq!(
+            "path,algorithm,digest,size,mode,status,error\n\
+             \"a,\"\"b\"\"\",md5,00,3,text,ok,\n\
+             c,md5,,,binary,error,No such file or directory\n",
+            output(OutputFormat::Csv, None)
+        );
+        assert_eq!("", csv_field(&Value::Null));
+    }
+
+    #[test]
+    fn csv_summary() {
+        let mut buffer = vec![];
+        let writer = RecordWriter::new(OutputFormat::Csv, CHECK_FIELDS, &mut buffer)
+            .unwrap()
+            .unwrap();
+        writer
+            .finish(Some(json!({"failed": 1, "success": false})))
+            .unwrap();
+        assert_eq!(
+            "path,expected,actual,status,error\n\
+             ,,,summary,failed=1 success=false\n",
+            String::from_utf8(buffer).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_records() {
+        let document: Value = serde_json::from_str(&output(OutputFormat::Json, None)).unwrap();
+        assert_eq!(json!("c"), document[1]["path"]);
+
+        let document: Value =
+            serde_json::from_str(&output(OutputFormat::Json, Some(json!({"verified": 2}))))
+                .unwrap();
+        assert_eq!(json!(3), document["files"][0]["size"]);
+        assert_eq!(json!({"verified": 2}), document["summary"]);
+
+        let lines = output(OutputFormat::Ndjson, Some(json!({"verified": 2})));
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(3, lines.len());
+        assert_eq!(r#"{"summary":{"verified":2}}"#, lines[2]);
+
+        assert!(
+            RecordWriter::new(OutputFormat::Text, HASH_FIELDS, vec![])
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Reports of the check results for the CI systems, in the Test Anything
+//! Protocol or JUnit XML. Each check file is a test suite, and each of its
+//! lines a test case.
+
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Format of the report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Tap,
+    Junit,
+}
+
+/// Why a test case failed
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    /// Kind of failure, the type of the JUnit failures
+    pub kind: &'static str,
+    pub message: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl Failure {
+    /// The message with the expected and the actual digests
+    fn details(&self) -> String {
+        format!(
+            "{}\nexpected: {}\nactual: {}",
+            self.message,
+            self.expected.as_deref().unwrap_or("none"),
+            self.actual.as_deref().unwrap_or("none")
+        )
+    }
+}
+
+/// Result of a line of a check file
+#[derive(Debug, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    pub failure: Option<Failure>,
+}
+
+/// Test cases of every check file
+#[derive(Debug)]
+pub struct Report {
+    format: ReportFormat,
+    /// The name of each check file and its test cases
+    suites: Vec<(String, Vec<TestCase>)>,
+}
+
+impl Report {
+    pub fn new(format: ReportFormat) -> Report {
+        Report {
+            format,
+            suites: vec![],
+        }
+    }
+
+    pub fn add_suite(&mut self, name: String, cases: Vec<TestCase>) {
+        self.suites.push((name, cases));
+    }
+
+    /// Returns the report in the selected format
+    pub fn render(&self) -> String {
+        match self.format {
+            ReportFormat::Tap => self.to_tap(),
+            ReportFormat::Junit => self.to_junit(),
+        }
+    }
+
+    /// TAP version 13, the failures have a YAML block with the message and
+    /// the digests. The YAML values are written as JSON strings.
+    fn to_tap(&self) -> String {
+        let total = self
+            .suites
+            .iter()
+            .map(|(_, cases)| cases.len())
+            .sum::<usize>();
+        let mut output = format!("TAP version 13\n1..{}\n", total);
+        let cases = self
+            .suites
+            .iter()
+            .flat_map(|(suite, cases)| cases.iter().map(move |case| (suite, case)));
+        for (idx, (suite, case)) in cases.enumerate() {
+            // A # would start a directive
+            let name = case.name.replace('#', "\\#");
+            let Some(failure) = &case.failure else {
+                writeln!(output, "ok {} - {}", idx + 1, name).unwrap();
+                continue;
+            };
+            writeln!(output, "not ok {} - {}", idx + 1, name).unwrap();
+            writeln!(output, "  ---").unwrap();
+            writeln!(
+                output,
+                "  message: {}",
+                Value::from(failure.message.as_str())
+            )
+            .unwrap();
+            writeln!(output, "  type: {}", failure.kind).unwrap();
+            writeln!(output, "  manifest: {}", Value::from(suite.as_str())).unwrap();
+            for (key, digest) in [("expected", &failure.expected), ("actual", &failure.actual)] {
+                let digest = digest.as_deref().map_or(Value::Null, Value::from);
+                writeln!(output, "  {}: {}", key, digest).unwrap();
+            }
+            writeln!(output, "  ...").unwrap();
+        }
+        output
+    }
+
+    fn to_junit(&self) -> String {
+        let failures = |cases: &[TestCase]| cases.iter().filter(|c| c.failure.is_some()).count();
+        let total = self
+            .suites
+            .iter()
+            .map(|(_, cases)| cases.len())
+            .sum::<usize>();
+        let total_failures = self
+            .suites
+            .iter()
+            .map(|(_, cases)| failures(cases))
+            .sum::<usize>();
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        writeln!(
+            output,
+            "<testsuites name=\"md5sum\" tests=\"{}\" failures=\"{}\">",
+            total, total_failures
+        )
+        .unwrap();
+        for (suite, cases) in self.suites.iter() {
+            let suite = xml_escape(suite);
+            writeln!(
+                output,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+                suite,
+                cases.len(),
+                failures(cases)
+            )
+            .unwrap();
+            for case in cases {
+                let name = xml_escape(&case.name);
+                let Some(failure) = &case.failure else {
+                    writeln!(
+                        output,
+                        "    <testcase classname=\"{}\" name=\"{}\"/>",
+                        suite, name
+                    )
+                    .unwrap();
+                    continue;
+                };
+                writeln!(
+                    output,
+                    "    <testcase classname=\"{}\" name=\"{}\">",
+                    suite, name
+                )
+                .unwrap();
+                writeln!(
+                    output,
+                    "      <failure type=\"{}\" message=\"{}\">{}</failure>",
+                    failure.kind,
+                    xml_escape(&failure.message),
+                    xml_escape(&failure.details())
+                )
+                .unwrap();
+                writeln!(output, "    </testcase>").unwrap();
+            }
+            writeln!(output, "  </testsuite>").unwrap();
+        }
+        writeln!(output, "</testsuites>").unwrap();
+        output
+    }
+}
+
+/// Escapes the XML special characters, the control characters not allowed
+/// in XML 1.0 are replaced
+fn xml_escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            '\n' | '\r' | '\t' => write!(output, "&#{};", c as u32).unwrap(),
+            c if c.is_control() => output.push(char::REPLACEMENT_CHARACTER),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::*;
+
+    fn report(format: ReportFormat) -> Report {
+        let mut report = Report::new(format);
+        report.add_suite(
+            "sums.md5".to_owned(),
+            vec![
+                TestCase {
+                    name: "a#1".to_owned(),
+                    failure: None,
+                },
+                TestCase {
+                    name: "<b>".to_owned(),
+                    failure: Some(Failure {
+                        kind: "mismatch",
+                        message: "checksum mismatch".to_owned(),
+                        expected: Some("00".to_owned()),
+                        actual: Some("11".to_owned()),
+                    }),
+                },
+            ],
+        );
+        report
+    }
+
+    #[test]
+    fn tap_report() {
+        assert_eq!(
+            "TAP version 13\n\
+             1..2\n\
+             ok 1 - a\\#1\n\
+             not ok 2 - <b>\n  \
+               ---\n  \
+               message: \"checksum mismatch\"\n  \
+               type: mismatch\n  \
+               manifest: \"sums.md5\"\n  \
+               expected: \"00\"\n  \
+               actual: \"11\"\n  \
+               ...\n",
+            report(ReportFormat::Tap).render()
+        );
+    }
+
+    #[test]
+    fn junit_report() {
+        let output = report(ReportFormat::Junit).render();
+        assert!(output.contains("<testsuites name=\"md5sum\" tests=\"2\" failures=\"1\">"));
+        assert!(output.contains("<testcase classname=\"sums.md5\" name=\"a#1\"/>"));
+        assert!(output.contains(
+            "<testcase classname=\"sums.md5\" name=\"&lt;b&gt;\">\n      \
+             <failure type=\"mismatch\" message=\"checksum mismatch\">\
+             checksum mismatch&#10;expected: 00&#10;actual: 11</failure>"
+        ));
+        assert_eq!("a&amp;b&#10;\u{fffd}", xml_escape("a&b\n\u{1}"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Directory walking of the recursive mode. The files of each root are
+//! returned sorted by the bytes of their names, so the order doesn't depend
+//! on the locale.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Options of the recursive mode
+#[derive(Debug, Default)]
+pub struct WalkOptions {
+    /// Only the files matching any of these globs are hashed
+    pub include: Vec<String>,
+    /// The files and directories matching any of these globs are skipped
+    pub exclude: Vec<String>,
+    pub follow_symlinks: bool,
+    pub one_file_system: bool,
+    /// Skip the files ignored by the .gitignore files
+    pub gitignore: bool,
+}
+
+/// Path of a file found walking and the name printed in the output
+pub type WalkedFile = (OsString, OsString);
+
+fn build_glob_set(globs: &[String]) -> Result<GlobSet, Box<dyn Error>> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Walks the roots and returns the path of every file found with its name
+/// relative to the root, so the output can be checked from the root. The
+/// roots that aren't directories are returned as they are. The symbolic
+/// links to files are returned as files, the ones to directories are only
+/// walked when following the links. The errors found walking are printed
+/// and counted.
+pub fn walk(
+    roots: &[OsString],
+    options: &WalkOptions,
+) -> Result<(Vec<WalkedFile>, usize), Box<dyn Error>> {
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+    let mut files = vec![];
+    let mut error_counter = 0;
+
+    for root in roots {
+        let root_path = Path::new(root);
+        if root == "-" || !root_path.is_dir() {
+            files.push((root.clone(), root.clone()));
+            continue;
+        }
+
+        let exclude_root = exclude.clone();
+        let root_dir = root_path.to_owned();
+        let walker = WalkBuilder::new(root_path)
+            .standard_filters(false)
+            .git_ignore(options.gitignore)
+            .git_exclude(options.gitignore)
+            .parents(options.gitignore)
+            .require_git(false)
+            .follow_links(options.follow_symlinks)
+            .same_file_system(options.one_file_system)
+            .filter_entry(move |entry| {
+                // The excluded directories are not walked
+                let relative = entry.path().strip_prefix(&root_dir).unwrap_or(entry.path());
+                entry.depth() == 0 || !exclude_root.is_match(relative)
+            })
+            .build();
+
+        let mut root_files = vec![];
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("Couldn't walk {}: {}", root_path.display(), e);
+                    error_counter += 1;
+                    continue;
+                }
+            };
+            match entry.file_type() {
+                Some(file_type) if file_type.is_file() => {}
+                // The links that were not followed, a dangling link is
+                // returned so reading it is reported
+                Some(file_type) if file_type.is_symlink() => {
+                    if fs::metadata(entry.path()).is_ok_and(|metadata| metadata.is_dir()) {
+                        eprintln!(
+                            "Skipping the symbolic link to a directory {}, use --follow-symlinks",
+                            entry.path().display()
+                        );
+                        continue;
+                    }
+                }
+                _ => continue,
+            }
+            let relative = entry.path().strip_prefix(root_path).unwrap_or(entry.path());
+            if !options.include.is_empty() && !include.is_match(relative) {
+                continue;
+            }
+            root_files.push((
+                entry.path().as_os_str().to_owned(),
+                relative.as_os_str().to_owned(),
+            ));
+        }
+        root_files.sort_by(|(_, a), (_, b)| a.as_bytes().cmp(b.as_bytes()));
+        files.extend(root_files);
+    }
+    Ok((files, error_counter))
+}
+
+#[cfg(test)]
+mod walk_tests {
+    use super::*;
+    use std::fs;
+
+    fn names(root: &Path, options: &WalkOptions) -> Vec<String> {
+        let (files, errors) = walk(&[root.as_os_str().to_owned()], options).unwrap();
+        assert_eq!(0, errors);
+        files
+            .into_iter()
+            .map(|(_, name)| name.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn walk_sorted_and_filtered() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let root = temp.path();
+        for dir in ["b", "a/target", "a/src", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "b/Z.txt",
+            "b/a.txt",
+            "a/src/main.rs",
+            "a/target/out.o",
+            ".hidden/file",
+            "B.txt",
+            "a-b.txt",
+        ] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        fs::write(root.join(".gitignore"), "*.o\n").unwrap();
+        std::os::unix::fs::symlink("B.txt", root.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink("a/src", root.join("link")).unwrap();
+
+        // The links to files are hashed, the ones to directories skipped
+        let mut options = WalkOptions::default();
+        assert_eq!(
+            vec![
+                ".gitignore",
+                ".hidden/file",
+                "B.txt",
+                "a-b.txt",
+                "a/src/main.rs",
+                "a/target/out.o",
+                "b/Z.txt",
+                "b/a.txt",
+                "link.txt"
+            ],
+            names(root, &options)
+        );
+
+        options.follow_symlinks = true;
+        assert!(names(root, &options).contains(&"link/main.rs".to_owned()));
+        options.follow_symlinks = false;
+
+        options.gitignore = true;
+        assert!(!names(root, &options).contains(&"a/target/out.o".to_owned()));
+
+        options.gitignore = false;
+        options.include = vec!["*.txt".to_owned(), "*.rs".to_owned()];
+        options.exclude = vec!["b".to_owned()];
+        assert_eq!(
+            vec!["B.txt", "a-b.txt", "a/src/main.rs", "link.txt"],
+            names(root, &options)
+        );
+    }
+}
@@ -147,4 +147,1136 @@ mod md5sum_functional_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn hash_sparse_file() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("sparse_file.img")?;
+        std::fs::File::create(file.path())?.set_len((1 << 24) + 1)?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--buffer-size").arg("4096").arg(file.path());
+        cmd.assert().success().stdout(predicate::str::starts_with(
+            "cbcda39ca2893010c1d15c51bc633b24  ",
+        ));
+
+        Ok(())
+    }
+
+    // Hashes a file larger than the memory of most build agents, run with
+    // cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn hash_large_sparse_file() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("sparse_file.img")?;
+        std::fs::File::create(file.path())?.set_len(5 << 30)?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg(file.path());
+        cmd.assert().success().stdout(predicate::str::starts_with(
+            "ec4bcc8776ea04479b786e063a9ace45  ",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stdin_and_file_digests_match() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let contents = "a\nb\n\r\n\u{00e9}\0\n";
+        file.write_str(contents)?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg(file.path());
+        let output = cmd.output()?;
+        let file_digest = String::from_utf8(output.stdout)?[..32].to_owned();
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.write_stdin(contents);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(format!("{}  -\n", file_digest)));
+
+        // printf 'a\nb\n' | md5sum
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-");
+        cmd.write_stdin("a\nb\n");
+        cmd.assert().success().stdout(predicate::str::diff(
+            "dd8c6a395b5dd36c56d23275028f526c  -\n",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stdin_binary_marker() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-b");
+        cmd.write_stdin(vec![0xff_u8, 0xfe, 0x00, 0x0a]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::ends_with(" *-\n"));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--tag");
+        cmd.write_stdin("123456");
+        cmd.assert().success().stdout(predicate::str::diff(
+            "MD5 (-) = e10adc3949ba59abbe56e057f20f883e\n",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_and_binary_mode_hash_the_same_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("binary_file.bin")?;
+        // Not valid UTF-8
+        std::fs::write(file.path(), [0xff_u8, 0xfe, 0x00, 0x80, 0x0a])?;
+        let md5sum = "33ee9899d1850b01b8dfba8fa5c327b8";
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg(file.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::starts_with(format!("{}  ", md5sum)));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-b").arg(file.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::starts_with(format!("{} *", md5sum)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_errors_fail() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("first_file.txt")?;
+        file.write_str("123456")?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("kk.txt").arg(file.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::starts_with(
+                "e10adc3949ba59abbe56e057f20f883e  ",
+            ))
+            // The hash of an empty buffer is never printed for the missing file
+            .stdout(predicate::str::contains("d41d8cd98f00b204e9800998ecf8427e").not())
+            .stderr(predicate::str::contains("kk.txt"));
+
+        let check_file = assert_fs::NamedTempFile::new("check.md5")?;
+        check_file.write_str(&format!(
+            "e10adc3949ba59abbe56e057f20f883e  {}\nd41d8cd98f00b204e9800998ecf8427e  kk.txt\n",
+            file.path().display()
+        ))?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg(check_file.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains(": OK"))
+            .stdout(predicate::str::contains("kk.txt: FAILED open or read"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_binaries() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+
+        let vectors = [
+            (
+                cargo_bin_cmd!("sha1sum"),
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+            ),
+            (
+                cargo_bin_cmd!("sha224sum"),
+                "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+            ),
+            (
+                cargo_bin_cmd!("sha256sum"),
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                cargo_bin_cmd!("sha384sum"),
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+            ),
+            (
+                cargo_bin_cmd!("sha512sum"),
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                cargo_bin_cmd!("b2sum"),
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            ),
+        ];
+        for (mut cmd, hash) in vectors {
+            cmd.arg(file.path());
+            cmd.assert().success().stdout(predicate::str::diff(format!(
+                "{}  {}\n",
+                hash,
+                file.path().display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_algorithm() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+        let sm3 = "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0";
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-a").arg("sm3").arg("--tag").arg(file.path());
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "SM3 ({}) = {}\n",
+            file.path().display(),
+            sm3
+        )));
+
+        let check_file = assert_fs::NamedTempFile::new("check.sha256")?;
+        check_file.write_str(&format!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  {}\n",
+            file.path().display()
+        ))?;
+        let mut cmd = cargo_bin_cmd!("sha256sum");
+        cmd.arg("-c").arg(check_file.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains(": OK"));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--algorithm=md4").arg(file.path());
+        cmd.assert().failure();
+
+        Ok(())
+    }
+
+    #[test]
+    fn cksum_output_formats() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("abc")?;
+        file2.write_str("hello world\n")?;
+        let (name1, name2) = (file1.path().display(), file2.path().display());
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg(file1.path()).arg(file2.path());
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "1219131554 3 {}\n3733384285 12 {}\n",
+            name1, name2
+        )));
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg("-a").arg("bsd").arg(file1.path()).arg(file2.path());
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "16556     1 {}\n03762     1 {}\n",
+            name1, name2
+        )));
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg("-a").arg("sysv");
+        cmd.write_stdin("abc");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("294 1\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cksum_digests_are_tagged() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg("-a").arg("sha256").arg(file.path());
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "SHA256 ({}) = {}\n",
+            file.path().display(),
+            sha256
+        )));
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg("-a")
+            .arg("sha256")
+            .arg("--untagged")
+            .arg(file.path());
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "{}  {}\n",
+            sha256,
+            file.path().display()
+        )));
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg("-a").arg("crc").arg("-c").arg(file.path());
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("--check is not supported"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_tagged_and_mixed_manifests() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+
+        // The output of --tag can be verified by --check
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--tag").arg(file.path());
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let check_file = assert_fs::NamedTempFile::new("check.md5")?;
+        check_file.write_str(std::str::from_utf8(&output)?)?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg(check_file.path());
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "{}: OK\n",
+            file.path().display()
+        )));
+
+        // cksum takes the algorithm of each line
+        let manifest = assert_fs::NamedTempFile::new("manifest.txt")?;
+        manifest.write_str(&format!(
+            "# mixed manifest\r\n\
+             SHA256 ({0}) = ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\r\n\
+             900150983cd24fb0d6963f7d28e17f72  {0}\r\n\
+             BLAKE2b ({0}) = ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923\n",
+            file.path().display()
+        ))?;
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.arg("-c").arg(manifest.path());
+        cmd.assert().success().stdout(predicate::str::diff(
+            format!("{}: OK\n", file.path().display()).repeat(3),
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_output_modes() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+        let name = file.path().display();
+        let check_file = assert_fs::NamedTempFile::new("check.md5")?;
+        check_file.write_str(&format!(
+            "900150983cd24fb0d6963f7d28e17f72  {0}\n\
+             not a checksum line\n\
+             d41d8cd98f00b204e9800998ecf8427e  {0}\n\
+             d41d8cd98f00b204e9800998ecf8427e  {0}\n",
+            name
+        ))?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg(check_file.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::diff(format!(
+                "{0}: OK\n{0}: FAILED\n{0}: FAILED\n",
+                name
+            )))
+            .stderr(predicate::str::contains(
+                "WARNING: 1 line is improperly formatted",
+            ))
+            .stderr(predicate::str::contains(
+                "WARNING: 2 computed checksums did NOT match",
+            ))
+            .stderr(predicate::str::contains("improperly formatted checksum line\n").not());
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg("--quiet").arg(check_file.path());
+        cmd.assert().failure().stdout(predicate::str::diff(format!(
+            "{0}: FAILED\n{0}: FAILED\n",
+            name
+        )));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg("--status").arg(check_file.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::is_empty())
+            .stderr(predicate::str::is_empty());
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg("--warn").arg(check_file.path());
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "{}: 2: improperly formatted checksum line",
+                check_file.path().display()
+            )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_strict_and_ignore_missing() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+        let check_file = assert_fs::NamedTempFile::new("check.md5")?;
+        check_file.write_str(&format!(
+            "900150983cd24fb0d6963f7d28e17f72  {}\nnot a checksum line\n",
+            file.path().display()
+        ))?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg("--status").arg(check_file.path());
+        cmd.assert().success();
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg("--strict").arg(check_file.path());
+        cmd.assert().failure();
+
+        let missing_file = assert_fs::NamedTempFile::new("missing.md5")?;
+        missing_file.write_str(&format!(
+            "900150983cd24fb0d6963f7d28e17f72  {}\n\
+             d41d8cd98f00b204e9800998ecf8427e  missing.txt\n",
+            file.path().display()
+        ))?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg(missing_file.path());
+        cmd.assert().failure().stderr(predicate::str::contains(
+            "WARNING: 1 listed file could not be read",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c")
+            .arg("--ignore-missing")
+            .arg(missing_file.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("missing.txt").not());
+
+        // Nothing verified at all is a failure
+        let missing_only = assert_fs::NamedTempFile::new("missing_only.md5")?;
+        missing_only.write_str("d41d8cd98f00b204e9800998ecf8427e  missing.txt\n")?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c")
+            .arg("--ignore-missing")
+            .arg(missing_only.path());
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("no file was verified"));
+
+        // The options are only valid with --check
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--quiet").arg(file.path());
+        cmd.assert().failure();
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_every_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        let first = dir.child("first/abc.txt");
+        let second = dir.child("second/empty.txt");
+        first.write_str("abc")?;
+        second.write_str("")?;
+        let first_manifest = dir.child("first/check.md5");
+        let second_manifest = dir.child("second/check.md5");
+        first_manifest.write_str("900150983cd24fb0d6963f7d28e17f72  abc.txt\n")?;
+        second_manifest.write_str("d41d8cd98f00b204e9800998ecf8427e  empty.txt\n")?;
+
+        // The listed files are relative to the directory of each manifest
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("-c")
+            .arg("--relative-to-manifest")
+            .arg("first/check.md5")
+            .arg("second/check.md5")
+            .arg("-")
+            .write_stdin("d41d8cd98f00b204e9800998ecf8427e  second/empty.txt\n");
+        cmd.assert().success().stdout(predicate::str::diff(
+            "first/abc.txt: OK\nsecond/empty.txt: OK\nsecond/empty.txt: OK\n",
+        ));
+
+        // And relative to the current directory by default
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("-c")
+            .arg("first/check.md5")
+            .arg("second/check.md5");
+        cmd.assert().failure().stdout(predicate::str::diff(
+            "abc.txt: FAILED open or read\nempty.txt: FAILED open or read\n",
+        ));
+
+        // A failed manifest doesn't stop the verification of the others
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path().join("first"))
+            .arg("-c")
+            .arg("missing.md5")
+            .arg("check.md5");
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::diff("abc.txt: OK\n"))
+            .stderr(predicate::str::contains("missing.md5"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_compares_only_digests() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("abc.txt")?;
+        file.write_str("abc")?;
+        let check_file = assert_fs::NamedTempFile::new("check.md5")?;
+        check_file.write_str(&format!(
+            "900150983CD24FB0D6963F7D28E17F72 *{0}\n900150983cd24fb0d6963f7d28e17f72  {0}\n",
+            file.path().display()
+        ))?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("-c").arg(check_file.path());
+        cmd.assert().success().stdout(predicate::str::diff(
+            format!("{}: OK\n", file.path().display()).repeat(2),
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn escaped_file_names() -> Result<(), Box<dyn std::error::Error>> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = assert_fs::TempDir::new()?;
+        let name = OsStr::from_bytes(b"new\nline\\back\xff");
+        std::fs::write(dir.path().join(name), "abc")?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).arg(name);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        assert_eq!(
+            b"\\900150983cd24fb0d6963f7d28e17f72  new\\nline\\\\back\xff\n".to_vec(),
+            output
+        );
+
+        // The escaped lines are verified and the results escaped too
+        std::fs::write(dir.path().join("check.md5"), &output)?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).arg("-c").arg("check.md5");
+        cmd.assert()
+            .success()
+            .stdout(predicate::eq(b"\\new\\nline\\\\back\xff: OK\n".as_slice()));
+
+        // -z prints the names as they are
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).arg("-z").arg(name);
+        cmd.assert().success().stdout(predicate::eq(
+            b"900150983cd24fb0d6963f7d28e17f72  new\nline\\back\xff\0".as_slice(),
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_jobs_keep_order() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::{FileWriteBin, PathChild};
+
+        let dir = assert_fs::TempDir::new()?;
+        let big_file = dir.child("big.bin");
+        big_file.write_binary(&vec![0; 8 * 1024 * 1024])?;
+        let mut args = vec!["big.bin".to_owned()];
+        let mut expected = String::from("96995b58d4cbf6aaa9041b4f00c7f6ae  big.bin\n");
+        for idx in 0..20 {
+            let name = format!("small_{idx:02}.txt");
+            dir.child(&name).write_str("abc")?;
+            expected.push_str(&format!("900150983cd24fb0d6963f7d28e17f72  {name}\n"));
+            args.push(name);
+        }
+        args.insert(10, "missing.txt".to_owned());
+
+        for jobs in ["1", "4"] {
+            let mut cmd = cargo_bin_cmd!("md5sum");
+            cmd.current_dir(dir.path())
+                .arg("--jobs")
+                .arg(jobs)
+                .args(&args);
+            cmd.assert()
+                .failure()
+                .stdout(predicate::str::diff(expected.clone()))
+                .stderr(predicate::str::contains("missing.txt"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("release/b.txt").write_str("abc")?;
+        dir.child("release/a/z.txt").write_str("")?;
+        dir.child("release/a/skip.log").write_str("log")?;
+        dir.child("release/target/out.txt").write_str("out")?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("-r")
+            .arg("--include")
+            .arg("*.txt")
+            .arg("--exclude")
+            .arg("target")
+            .arg("release");
+        let output = cmd.assert().success().get_output().stdout.clone();
+        assert_eq!(
+            "d41d8cd98f00b204e9800998ecf8427e  a/z.txt\n\
+             900150983cd24fb0d6963f7d28e17f72  b.txt\n",
+            std::str::from_utf8(&output)?
+        );
+
+        // The manifest is checked from the root
+        dir.child("release/check.md5")
+            .write_str(std::str::from_utf8(&output)?)?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("-c")
+            .arg("--relative-to-manifest")
+            .arg("release/check.md5");
+        cmd.assert().success();
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--include").arg("*.txt").arg(dir.path());
+        cmd.assert().failure();
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_manifests_and_trees() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        let old = dir.child("old.md5");
+        old.write_str(
+            "900150983cd24fb0d6963f7d28e17f72  abc.txt\n\
+             d41d8cd98f00b204e9800998ecf8427e  empty.txt\n\
+             e10adc3949ba59abbe56e057f20f883e  numbers.txt\n",
+        )?;
+        dir.child("tree/abc.txt").write_str("changed")?;
+        dir.child("tree/docs/empty.txt").write_str("")?;
+        dir.child("tree/new.txt").write_str("new")?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--diff")
+            .arg(old.path())
+            .arg(dir.child("tree").path());
+        cmd.assert().failure().stdout(predicate::str::diff(
+            "M  abc.txt\n\
+             R  empty.txt -> docs/empty.txt\n\
+             A  new.txt\n\
+             D  numbers.txt\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--diff")
+            .arg("--format")
+            .arg("json")
+            .arg(old.path())
+            .arg(dir.child("tree").path());
+        let output = cmd.assert().failure().get_output().stdout.clone();
+        let changes: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(serde_json::json!(["new.txt"]), changes["added"]);
+        assert_eq!(serde_json::json!(["numbers.txt"]), changes["removed"]);
+        assert_eq!(serde_json::json!(["abc.txt"]), changes["modified"]);
+        assert_eq!(
+            serde_json::json!([{"from": "empty.txt", "to": "docs/empty.txt"}]),
+            changes["renamed"]
+        );
+
+        // No changes between a manifest and itself
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.arg("--diff").arg(old.path()).arg(old.path());
+        cmd.assert().success().stdout(predicate::str::is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cached_digests() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        let file = dir.child("data.txt");
+        file.write_str("abc")?;
+        let cache = dir.child("digests.cache");
+        let abc_line = "900150983cd24fb0d6963f7d28e17f72  data.txt\n";
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("--cache")
+            .arg(cache.path())
+            .arg("data.txt");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(abc_line));
+        assert!(std::fs::read_to_string(cache.path())?.contains("data.txt"));
+
+        // Same size and modification time, the cached digest is used
+        let modified = std::fs::metadata(file.path())?.modified()?;
+        file.write_str("xyz")?;
+        std::fs::File::options()
+            .write(true)
+            .open(file.path())?
+            .set_modified(modified)?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("--cache")
+            .arg(cache.path())
+            .arg("data.txt");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(abc_line));
+
+        // Verifying every cached file finds the wrong digest
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("--cache")
+            .arg(cache.path())
+            .arg("--cache-verify=100")
+            .arg("data.txt");
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::diff(
+                "d16fb36f0911f878998c136191af705e  data.txt\n",
+            ))
+            .stderr(predicate::str::contains("doesn't match"))
+            .stderr(predicate::str::contains("1 cached digests didn't match"));
+
+        // And the cache was updated
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("--cache")
+            .arg(cache.path())
+            .arg("data.txt");
+        cmd.assert().success().stdout(predicate::str::starts_with(
+            "d16fb36f0911f878998c136191af705e",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_duplicates() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("a.txt").write_str("abc")?;
+        dir.child("sub/b.txt").write_str("abc")?;
+        dir.child("c.txt").write_str("abd")?;
+        // Same size, head and tail, only the middle is different
+        let big = |middle: &str| format!("{}{}{}", "x".repeat(5000), middle, "y".repeat(5000));
+        dir.child("big1").write_str(&big("1"))?;
+        dir.child("big2").write_str(&big("2"))?;
+        dir.child("big3").write_str(&big("1"))?;
+        dir.child("empty1").write_str("")?;
+        dir.child("empty2").write_str("")?;
+        let groups = "900150983cd24fb0d6963f7d28e17f72  ./a.txt\n\
+                      900150983cd24fb0d6963f7d28e17f72  ./sub/b.txt\n";
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .arg("--find-duplicates")
+            .arg(".");
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let output = String::from_utf8(output)?;
+        assert!(output.starts_with(groups));
+        assert_eq!(2, output.split("\n\n").count());
+        assert!(output.contains("  ./big1\n") && output.contains("  ./big3\n"));
+        assert!(!output.contains("big2") && !output.contains("empty"));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--find-duplicates", "--format=json", "a.txt", "sub"]);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let groups_json: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(
+            serde_json::json!({
+                "algorithm": "md5",
+                "groups": [{
+                    "digest": "900150983cd24fb0d6963f7d28e17f72",
+                    "size": 3,
+                    "files": ["a.txt", "sub/b.txt"],
+                }],
+            }),
+            groups_json
+        );
+
+        // The dry run doesn't change the files
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--find-duplicates", "--hardlink", "--dry-run", "."]);
+        cmd.assert().success().stderr(predicate::str::contains(
+            "Would link ./sub/b.txt to ./a.txt",
+        ));
+        let inode = |name: &str| std::fs::metadata(dir.child(name).path()).unwrap().ino();
+        assert_ne!(inode("a.txt"), inode("sub/b.txt"));
+
+        // The groups format is only for the duplicates
+        for args in [&["a.txt"][..], &["-c", "a.txt"]] {
+            let mut cmd = cargo_bin_cmd!("md5sum");
+            cmd.current_dir(dir.path())
+                .arg("--format=groups")
+                .args(args);
+            cmd.assert()
+                .failure()
+                .stdout(predicate::str::is_empty())
+                .stderr(predicate::str::contains(
+                    "--format=groups needs --find-duplicates",
+                ));
+        }
+
+        // The dry run needs an action
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--find-duplicates", "--dry-run", "."]);
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::is_empty())
+            .stderr(predicate::str::contains("--hardlink|--delete-keep-first"));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--find-duplicates", "--hardlink", "."]);
+        cmd.assert()
+            .success()
+            .stderr(predicate::str::contains("Linked ./sub/b.txt to ./a.txt"));
+        assert_eq!(inode("a.txt"), inode("sub/b.txt"));
+        assert_eq!(inode("big1"), inode("big3"));
+
+        // The hard links to the same file are not duplicates
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args(["--find-duplicates", "."]);
+        cmd.assert().success().stdout(predicate::str::is_empty());
+
+        dir.child("c_copy.txt").write_str("abd")?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--find-duplicates", "--delete-keep-first", "."]);
+        cmd.assert()
+            .success()
+            .stderr(predicate::str::diff("Deleted ./c_copy.txt\n"));
+        assert!(dir.child("c.txt").path().exists());
+        assert!(!dir.child("c_copy.txt").path().exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn machine_readable_formats() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("a.txt").write_str("abc")?;
+        dir.child("b,c.txt").write_str("")?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--format=csv", "a.txt", "b,c.txt"]);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "path,algorithm,digest,size,mode,status,error\n\
+             a.txt,md5,900150983cd24fb0d6963f7d28e17f72,3,text,ok,\n\
+             \"b,c.txt\",md5,d41d8cd98f00b204e9800998ecf8427e,0,text,ok,\n",
+        ));
+
+        // The files that can't be read are error records
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--format=ndjson", "missing.txt", "a.txt"]);
+        let output = cmd.assert().failure().get_output().stdout.clone();
+        let lines = output
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(2, lines.len());
+        assert_eq!("missing.txt", lines[0]["path"]);
+        assert_eq!("error", lines[0]["status"]);
+        assert!(lines[0]["digest"].is_null() && lines[0]["error"].is_string());
+        assert_eq!("ok", lines[1]["status"]);
+
+        let mut cmd = cargo_bin_cmd!("sha1sum");
+        cmd.current_dir(dir.path())
+            .args(["--format=json", "-b", "a.txt"]);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        assert_eq!(
+            serde_json::json!([{
+                "path": "a.txt",
+                "algorithm": "sha1",
+                "digest": "a9993e364706816aba3e25717850c26c9cd0d89d",
+                "size": 3,
+                "mode": "binary",
+                "status": "ok",
+                "error": null,
+            }]),
+            serde_json::from_slice::<serde_json::Value>(&output)?
+        );
+
+        dir.child("sums.md5").write_str(
+            "900150983cd24fb0d6963f7d28e17f72  a.txt\n\
+             00000000000000000000000000000000  b,c.txt\n\
+             d41d8cd98f00b204e9800998ecf8427e  missing.txt\n",
+        )?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--check", "--format=ndjson", "sums.md5"]);
+        let output = cmd.assert().failure().get_output().stdout.clone();
+        let lines = output
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(4, lines.len());
+        assert_eq!("ok", lines[0]["status"]);
+        assert_eq!("failed", lines[1]["status"]);
+        assert_eq!("00000000000000000000000000000000", lines[1]["expected"]);
+        assert_eq!("d41d8cd98f00b204e9800998ecf8427e", lines[1]["actual"]);
+        assert_eq!("missing", lines[2]["status"]);
+        assert!(lines[2]["actual"].is_null() && lines[2]["error"].is_string());
+        assert_eq!(
+            serde_json::json!({
+                "verified": 2,
+                "failed": 1,
+                "unreadable": 1,
+                "malformed": 0,
+                "success": false,
+            }),
+            lines[3]["summary"]
+        );
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--check", "--format=json", "--quiet", "sums.md5"]);
+        let output = cmd.assert().failure().get_output().stdout.clone();
+        let document: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(2, document["files"].as_array().unwrap().len());
+        assert_eq!(false, document["summary"]["success"]);
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--check", "--format=csv", "--quiet", "sums.md5"]);
+        cmd.assert().failure().stdout(predicate::str::ends_with(
+            ",,,summary,failed=1 malformed=0 success=false unreadable=1 verified=2\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--check", "--format=csv", "--status", "sums.md5"]);
+        cmd.assert().failure().stdout(predicate::str::is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_reports() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("a.txt").write_str("abc")?;
+        dir.child("b.txt").write_str("")?;
+        dir.child("sums.md5").write_str(
+            "900150983cd24fb0d6963f7d28e17f72  a.txt\n\
+             00000000000000000000000000000000  b.txt\n\
+             not a checksum line\n\
+             d41d8cd98f00b204e9800998ecf8427e  missing.txt\n",
+        )?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args([
+            "-c",
+            "--report=tap",
+            "--report-file=report.tap",
+            "sums.md5",
+        ]);
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("a.txt: OK"));
+        let tap = std::fs::read_to_string(dir.child("report.tap").path())?;
+        assert!(tap.starts_with("TAP version 13\n1..4\nok 1 - a.txt\nnot ok 2 - b.txt\n"));
+        assert!(tap.contains(
+            "  message: \"computed checksum did NOT match\"\n  \
+               type: mismatch\n  \
+               manifest: \"sums.md5\"\n  \
+               expected: \"00000000000000000000000000000000\"\n  \
+               actual: \"d41d8cd98f00b204e9800998ecf8427e\"\n"
+        ));
+        assert!(tap.contains("not ok 3 - missing.txt\n  ---\n  message: \"listed file is missing"));
+        assert!(tap.contains("not ok 4 - line 3\n  ---\n  message: \"improperly formatted"));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args([
+            "-c",
+            "--status",
+            "--report=junit",
+            "--report-file=report.xml",
+            "sums.md5",
+        ]);
+        cmd.assert().failure().stdout(predicate::str::is_empty());
+        let junit = std::fs::read_to_string(dir.child("report.xml").path())?;
+        assert!(junit.contains("<testsuite name=\"sums.md5\" tests=\"4\" failures=\"3\">"));
+        assert!(junit.contains("<testcase classname=\"sums.md5\" name=\"a.txt\"/>"));
+        for kind in ["mismatch", "missing", "parse-error"] {
+            assert!(junit.contains(&format!("<failure type=\"{kind}\"")));
+        }
+
+        // The report needs a file
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["-c", "--report=tap", "sums.md5"]);
+        cmd.assert().failure().code(2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn progress_disabled_without_terminal() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("a.txt").write_str("abc")?;
+        dir.child("sums.md5")
+            .write_str("900150983cd24fb0d6963f7d28e17f72  a.txt\n")?;
+
+        // stderr is a pipe, so nothing but the output is written
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args(["--progress", "a.txt"]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(
+                "900150983cd24fb0d6963f7d28e17f72  a.txt\n",
+            ))
+            .stderr(predicate::str::is_empty());
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--progress", "-c", "sums.md5"]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("a.txt: OK\n"))
+            .stderr(predicate::str::is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn byte_ranges() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        let data = dir.child("data.bin");
+        data.write_str(&"0123456789".repeat(205))?;
+
+        // The 10 bytes from 1K are 4567890123
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--offset", "1K", "--length", "10", "data.bin"]);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "5f2da0d908f0a5c2be219ab1ebec587f  data.bin@1024+10\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--head", "3", "--tag", "data.bin"]);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "MD5 (data.bin@0+3) = d2490f048dc3b77a457e3e450ab4eb38\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--tail", "3", "data.bin"]);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "68053af2923e00204c3ca7c6a3150cf7  data.bin@2047+3\n",
+        ));
+
+        // The ranges are verified, the file must have every byte of the range
+        dir.child("partial.md5").write_str(
+            "5f2da0d908f0a5c2be219ab1ebec587f  data.bin@1024+10\n\
+             MD5 (data.bin@0+3) = d2490f048dc3b77a457e3e450ab4eb38\n\
+             d41d8cd98f00b204e9800998ecf8427e  data.bin@2040+20\n",
+        )?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args(["-c", "partial.md5"]);
+        cmd.assert().failure().stdout(predicate::str::diff(
+            "data.bin@1024+10: OK\n\
+             data.bin@0+3: OK\n\
+             data.bin@2040+20: FAILED open or read\n",
+        ));
+
+        // A whole file named as a range is checked as the whole file
+        dir.child("x@1+2").write_str("abc")?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).arg("x@1+2");
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        assert_eq!("\\900150983cd24fb0d6963f7d28e17f72  x\\@1+2\n", output);
+        dir.child("named.md5").write_str(&output)?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args(["-c", "named.md5"]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("x@1+2: OK\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn encoded_digests() -> Result<(), Box<dyn std::error::Error>> {
+        use assert_fs::prelude::PathChild;
+
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("a.txt").write_str("abc")?;
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--encoding=base64", "a.txt"]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("kAFQmDzST7DWlj99KOF/cg==  a.txt\n"));
+
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path())
+            .args(["--encoding=base32", "--tag", "a.txt"]);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "MD5 (a.txt) = SAAVBGB42JH3BVUWH56SRYL7OI======\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("sha384sum");
+        cmd.current_dir(dir.path())
+            .args(["--encoding=sri", "a.txt"]);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "sha384-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn  a.txt\n",
+        ));
+
+        // The encoding of every line is detected
+        dir.child("mixed.md5").write_str(
+            "900150983cd24fb0d6963f7d28e17f72  a.txt\n\
+             kAFQmDzST7DWlj99KOF/cg==  a.txt\n\
+             MD5 (a.txt) = SAAVBGB42JH3BVUWH56SRYL7OI======\n\
+             md5-kAFQmDzST7DWlj99KOF/cg==  a.txt\n\
+             md5-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn  a.txt\n",
+        )?;
+        let mut cmd = cargo_bin_cmd!("md5sum");
+        cmd.current_dir(dir.path()).args(["-c", "mixed.md5"]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(
+                "a.txt: OK\na.txt: OK\na.txt: OK\na.txt: OK\n",
+            ))
+            .stderr(predicate::str::contains("1 line is improperly formatted"));
+
+        let mut cmd = cargo_bin_cmd!("cksum");
+        cmd.current_dir(dir.path())
+            .args(["-a", "sysv", "--encoding=base64", "a.txt"]);
+        cmd.assert().failure();
+
+        Ok(())
+    }
 }
@@ -0,0 +1,164 @@
+use clap::{Arg, ArgAction, command};
+use regex::bytes::Regex;
+use std::error::Error;
+use std::io::{self, Write};
+use utils::pipeline::{Eol, OutputFlags, SqueezeMode};
+use utils::reverse::{ReversedOutput, Separator, open_seekable};
+
+/// Builds the record separator from the command line value,
+/// as a regular expression if regex is set
+fn parse_separator(separator: &str, regex: bool) -> Result<Separator, Box<dyn Error>> {
+    if separator.is_empty() {
+        return Err("separator cannot be empty".into());
+    }
+    if regex {
+        Ok(Separator::Regex(Regex::new(separator)?))
+    } else {
+        Ok(Separator::Literal(separator.as_bytes().to_vec()))
+    }
+}
+
+/// Writes every line followed by the separator
+fn print_output(handle: &mut impl Write, data: &[Vec<u8>], separator: &[u8]) -> io::Result<()> {
+    for line in data.iter() {
+        handle.write_all(line)?;
+        handle.write_all(separator)?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = command!()
+        .about("Write each FILE to standard output, last line first.\n\nWith no FILE, or when file is -, read standard input.")
+        .arg(Arg::new("FILE").action(ArgAction::Append))
+        .arg(
+            Arg::new("separator")
+                .short('s')
+                .long("separator")
+                .value_name("SEP")
+                .default_value("\n")
+                .hide_default_value(true)
+                .help("use SEP instead of newline as the record separator"),
+        )
+        .arg(
+            Arg::new("regex")
+                .short('r')
+                .long("regex")
+                .action(ArgAction::SetTrue)
+                .help("interpret the separator as a regular expression"),
+        )
+        .arg(
+            Arg::new("numbers")
+                .short('n')
+                .long("numbers")
+                .action(ArgAction::SetTrue)
+                .help("Prepends line numbers to the output"),
+        )
+        .arg(
+            Arg::new("number-original")
+                .long("number-original")
+                .action(ArgAction::SetTrue)
+                .help("number the lines with their position in the FILE, implies -n"),
+        )
+        .arg(
+            Arg::new("show-all")
+                .short('A')
+                .long("show-all")
+                .action(ArgAction::SetTrue)
+                .help("equivalent to -ET"),
+        )
+        .arg(
+            Arg::new("show-ends")
+                .short('E')
+                .long("show-ends")
+                .action(ArgAction::SetTrue)
+                .help("display $ at the end of each line"),
+        )
+        .arg(
+            Arg::new("show-tabs")
+                .short('T')
+                .long("show-tabs")
+                .action(ArgAction::SetTrue)
+                .help("display TAB character as ^I"),
+        )
+        .get_matches();
+
+    let mut input_files = matches
+        .get_many::<String>("FILE")
+        .unwrap_or_default()
+        .map(|v| v.as_str())
+        .collect::<Vec<_>>();
+
+    // If no files were defined, force the use of stdin
+    if input_files.is_empty() {
+        input_files.push("-");
+    }
+
+    let separator = parse_separator(
+        matches.get_one::<String>("separator").unwrap(),
+        matches.get_flag("regex"),
+    )?;
+
+    let output_flags = OutputFlags {
+        numbers: matches.get_flag("numbers") || matches.get_flag("number-original"),
+        squeeze_blank: false,
+        squeeze_mode: SqueezeMode::Strict,
+        max_blank: 1,
+        number_noblank: false,
+        show_ends: matches.get_flag("show-ends") || matches.get_flag("show-all"),
+        show_tabs: matches.get_flag("show-tabs") || matches.get_flag("show-all"),
+        eol: Eol::Keep,
+        number_descending: matches.get_flag("number-original"),
+        number_width: 0,
+    };
+
+    let mut errors = vec![];
+    let mut handle = io::BufWriter::new(io::stdout());
+    let mut empty_line_counter = 0;
+    let mut last_line_number = 0;
+    'inputs: for fname in input_files.iter() {
+        let mut output = match open_seekable(fname).and_then(|reader| {
+            ReversedOutput::new(
+                reader,
+                separator.clone(),
+                &output_flags,
+                empty_line_counter,
+                last_line_number,
+            )
+        }) {
+            Ok(output) => output,
+            Err(e) => {
+                errors.push((format!("Error reading file {fname}"), e));
+                continue;
+            }
+        };
+        for record in output.by_ref() {
+            match record {
+                Ok((contents, separator)) => {
+                    if let Err(e) = print_output(&mut handle, &contents, &separator) {
+                        errors.push((String::from("Error writing output"), e));
+                        break 'inputs;
+                    }
+                }
+                Err(e) => {
+                    errors.push((format!("Error reading file {fname}"), e));
+                    break;
+                }
+            }
+        }
+        (empty_line_counter, last_line_number) = output.counters();
+    }
+    if let Err(e) = handle.flush() {
+        errors.push((String::from("Error writing output"), e));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        let error_output = String::from("Errors were reporting while procesing command.");
+        for (idx, e) in errors.into_iter().enumerate() {
+            eprintln!("\t Err {}  {}: {}", idx, e.0, e.1);
+        }
+        Err(error_output.into())
+    }
+}
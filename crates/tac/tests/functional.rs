@@ -0,0 +1,151 @@
+use assert_cmd::assert::OutputAssertExt;
+use assert_cmd::cargo::*;
+use assert_fs::prelude::FileWriteStr;
+use predicates::prelude::*;
+
+mod tac_functional_tests {
+
+    use super::*;
+
+    #[test]
+    fn reverse_file_contents() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("Line 1\nLine 2\n")?;
+        file2.write_str("Line 3\nLine 4")?;
+
+        let mut cmd = cargo_bin_cmd!("tac");
+
+        cmd.arg(file1.path()).arg(file2.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("Line 2\nLine 1\nLine 4\nLine 3\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_and_number() -> Result<(), Box<dyn std::error::Error>> {
+        let file1 = assert_fs::NamedTempFile::new("first_file.txt")?;
+        let file2 = assert_fs::NamedTempFile::new("second_file.txt")?;
+        file1.write_str("Line\t1\nLine\t2\n")?;
+        file2.write_str("Line\t3\nLine\t4\n")?;
+
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("-nTE").arg(file1.path()).arg(file2.path());
+        cmd.assert().success().stdout(predicate::str::diff(
+            "1 Line^I2$\n2 Line^I1$\n3 Line^I4$\n4 Line^I3$\n",
+        ));
+
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("--number-original")
+            .arg(file1.path())
+            .arg(file2.path());
+        cmd.assert().success().stdout(predicate::str::diff(
+            "2 Line\t2\n1 Line\t1\n4 Line\t4\n3 Line\t3\n",
+        ));
+
+        // The numbers are aligned to the width of the line count
+        let data = (1..=12).map(|i| format!("{i}\n")).collect::<String>();
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("-n").write_stdin(data.clone());
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let output = String::from_utf8(output)?;
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(vec![" 1 12", " 2 11"], lines[..2]);
+        assert_eq!("12 1", lines[11]);
+
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("--number-original").write_stdin(data);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let output = String::from_utf8(output)?;
+        assert!(output.starts_with("12 12\n11 11\n"));
+        assert!(output.ends_with(" 1 1\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_with_separators() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("-s").arg(",");
+        cmd.write_stdin("a,b,c,");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("c,b,a,"));
+
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("-r").arg("-s").arg("[0-9]+");
+        cmd.write_stdin("a1b22c333");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("c333b22a1"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_invalid_utf8() -> Result<(), Box<dyn std::error::Error>> {
+        // The bytes of the records are written back as they are
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.write_stdin(&b"a\n\xff\nb\n"[..]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::eq(&b"b\n\xff\na\n"[..]));
+
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg("-nT");
+        cmd.write_stdin(&b"\xfe\t\n\xff\n"[..]);
+        cmd.assert()
+            .success()
+            .stdout(predicate::eq(&b"1 \xff\n2 \xfe^I\n"[..]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_large_file() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("large_file.txt")?;
+        let lines: Vec<String> = (1..=20000).map(|i| format!("Line {i}\n")).collect();
+        file.write_str(&lines.concat())?;
+
+        let mut cmd = cargo_bin_cmd!("tac");
+        cmd.arg(file.path());
+        let expected: String = lines.iter().rev().map(|l| l.as_str()).collect();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_error() -> Result<(), Box<dyn std::error::Error>> {
+        let missing = assert_fs::NamedTempFile::new("missing.txt")?;
+        let file = assert_fs::NamedTempFile::new("file.txt")?;
+        file.write_str("Line 1\nLine 2\n")?;
+        let full = std::fs::File::options().write(true).open("/dev/full")?;
+
+        // The read errors are reported with the write error
+        let mut cmd = std::process::Command::new(cargo_bin!("tac"));
+        cmd.arg(missing.path()).arg(file.path()).stdout(full);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("Error reading file"))
+            .stderr(predicate::str::contains("Error writing output"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_doesnt_exist() -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = cargo_bin_cmd!("tac");
+
+        cmd.arg("kk.txt");
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("Error"));
+
+        Ok(())
+    }
+} // mod
@@ -0,0 +1,4 @@
+//! Code shared by the command line tools of the workspace.
+
+pub mod pipeline;
+pub mod reverse;
@@ -0,0 +1,445 @@
+//! Line formatting pipeline shared by the tools that print text line
+//! by line, cat and tac. The lines are bytes, so the input is written
+//! back exactly even if it is not valid UTF-8.
+
+/// Line ending style applied to the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eol {
+    Keep,
+    Lf,
+    Crlf,
+}
+
+/// Lines considered blank when squeezing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SqueezeMode {
+    Strict,
+    Whitespace,
+}
+
+/// A struct to store the parsed flags from the command line
+#[derive(Debug, Clone)]
+pub struct OutputFlags {
+    pub numbers: bool,
+    pub squeeze_blank: bool,
+    pub squeeze_mode: SqueezeMode,
+    pub max_blank: usize,
+    pub number_noblank: bool,
+    pub show_ends: bool,
+    pub show_tabs: bool,
+    pub eol: Eol,
+    /// Count the line numbers down, used when the lines are printed in
+    /// reverse but numbered with their original position
+    pub number_descending: bool,
+    /// Width of the line numbers, 0 to use the width of the number of
+    /// lines formatted at once
+    pub number_width: usize,
+}
+
+/// Takes a strins that may have  one or
+/// more EOL characters and separaes the lines to return a vector
+/// of single line strigns.
+pub fn unwrap_lines(data: &[u8]) -> Vec<Vec<u8>> {
+    data.split(|&byte| byte == b'\n')
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+/// Get a slice of string and replaces all tabs with ^I character
+fn replace_tabs(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    for &byte in data {
+        if byte == b'\t' {
+            output.extend_from_slice(b"^I");
+        } else {
+            output.push(byte);
+        }
+    }
+    output
+}
+
+/// Returns true if the line counts as blank for the squeeze mode,
+/// in whitespace mode lines with only spaces, tabs or CR are blank too
+fn is_blank(line: &[u8], mode: SqueezeMode) -> bool {
+    match mode {
+        SqueezeMode::Strict => line.is_empty(),
+        SqueezeMode::Whitespace => line.iter().all(|b| matches!(b, b' ' | b'\t' | b'\r')),
+    }
+}
+
+/// Returns the width of the number column, the width of the number of
+/// lines unless a width was selected
+fn number_width(data: &[Vec<u8>], width: usize) -> usize {
+    if width > 0 {
+        width
+    } else {
+        data.len().to_string().len()
+    }
+}
+
+/// Appends a line number at the beggining of every line,
+/// if the ignore_blanks flag is set, does not add a number to
+/// empty lines
+fn append_line_number(
+    data: &[Vec<u8>],
+    ignore_blanks: bool,
+    starting_number: usize,
+    width: usize,
+) -> (Vec<Vec<u8>>, usize) {
+    // Calculate the right alignment of the number column
+    let nc = number_width(data, width);
+
+    let mut line_number = starting_number;
+    let output = data
+        .iter()
+        .map(|line| {
+            let mut s = vec![];
+            if (!ignore_blanks) || (!line.is_empty()) {
+                line_number += 1;
+                s = format!("{:>nc$} ", line_number).into_bytes();
+                s.extend_from_slice(line);
+            }
+            s
+        })
+        .collect();
+
+    (output, line_number)
+}
+
+/// Same as append_line_number but the numbers count down from the
+/// starting number, which is never assigned to a line
+fn append_line_number_descending(
+    data: &[Vec<u8>],
+    ignore_blanks: bool,
+    starting_number: usize,
+    width: usize,
+) -> (Vec<Vec<u8>>, usize) {
+    let nc = number_width(data, width);
+
+    let mut line_number = starting_number;
+    let output = data
+        .iter()
+        .map(|line| {
+            let mut s = vec![];
+            if (!ignore_blanks) || (!line.is_empty()) {
+                line_number = line_number.saturating_sub(1);
+                s = format!("{:>nc$} ", line_number).into_bytes();
+                s.extend_from_slice(line);
+            }
+            s
+        })
+        .collect();
+
+    (output, line_number)
+}
+
+/// Removes the CR left at the end of every line by a CRLF line ending
+fn strip_carriage_return(data: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    data.iter()
+        .map(|x| x.strip_suffix(b"\r").unwrap_or(x).to_vec())
+        .collect()
+}
+
+/// Appends a CR to every line so the output ends in CRLF
+fn append_carriage_return(data: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    data.iter().map(|x| [x, &b"\r"[..]].concat()).collect()
+}
+
+fn append_eol_character(data: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    data.iter().map(|x| [x, &b"$"[..]].concat()).collect()
+}
+
+/// Keeps at most max_blank consecutive blank lines, the count of blank
+/// lines at the end of the previous input is carried in prev_emptylines
+fn remove_consecutive_empty_lines(
+    data: &[Vec<u8>],
+    prev_emptylines: usize,
+    max_blank: usize,
+    mode: SqueezeMode,
+) -> (Vec<Vec<u8>>, usize) {
+    let mut empty_line_counter = prev_emptylines;
+
+    let output: Vec<Vec<u8>> = data
+        .iter()
+        .filter(|line| {
+            if is_blank(line, mode) {
+                empty_line_counter += 1;
+            } else {
+                empty_line_counter = 0;
+            }
+            empty_line_counter <= max_blank
+        })
+        .map(|x| x.to_owned())
+        .collect();
+
+    (output, empty_line_counter)
+}
+
+/// Applies the formatting selected in the flags to the lines, the
+/// blank line counter and the last line number are carried between calls
+/// so the output of several inputs is formatted as a single one
+pub fn generate_output(
+    data: &[Vec<u8>],
+    output_flags: &OutputFlags,
+    empty_line_counter: usize,
+    last_line_number: usize,
+) -> (Vec<Vec<u8>>, usize, usize) {
+    // Split lines with mulitple end of line into separate vector entries
+    let mut output = data.to_owned();
+    let mut empty_line_counter = empty_line_counter;
+    let mut last_line_number = last_line_number;
+    // Normalize to LF first so blank CRLF lines are squeezed and not numbered by -b
+    if output_flags.eol != Eol::Keep {
+        output = strip_carriage_return(&output);
+    }
+    if output_flags.squeeze_blank {
+        (output, empty_line_counter) = remove_consecutive_empty_lines(
+            &output,
+            empty_line_counter,
+            output_flags.max_blank,
+            output_flags.squeeze_mode,
+        );
+    }
+    // Tabs are replaced after squeezing so whitespace only lines are still blank
+    if output_flags.show_tabs {
+        output = output.iter().map(|line| replace_tabs(line)).collect();
+    }
+    if output_flags.number_noblank || output_flags.numbers {
+        let number_lines = if output_flags.number_descending {
+            append_line_number_descending
+        } else {
+            append_line_number
+        };
+        (output, last_line_number) = number_lines(
+            &output,
+            output_flags.number_noblank,
+            last_line_number,
+            output_flags.number_width,
+        );
+    }
+    if output_flags.eol == Eol::Crlf {
+        output = append_carriage_return(&output);
+    }
+    if output_flags.show_ends {
+        output = append_eol_character(&output);
+    }
+
+    (output, empty_line_counter, last_line_number)
+}
+
+#[cfg(test)]
+mod pipeline_tests {
+    use super::*;
+
+    fn generate_test_string(n_lines: usize) -> String {
+        let mut output: String = "".to_owned();
+        match n_lines {
+            0 => output,
+            1 => String::from("Line 1"),
+            2.. => {
+                for i in 1..n_lines {
+                    output.push_str(&format!("Line {}\n", i));
+                }
+                output.push_str(&format!("Line {}", n_lines - 1));
+                output
+            }
+        }
+    }
+
+    fn generate_test_vector(n_lines: usize) -> Vec<Vec<u8>> {
+        let mut lines = vec![];
+        for i in 1..=n_lines {
+            lines.push(format!("Line {}", i).into_bytes());
+        }
+        lines
+    }
+
+    fn lines(lines: &[&str]) -> Vec<Vec<u8>> {
+        lines.iter().map(|line| line.as_bytes().to_vec()).collect()
+    }
+
+    fn first_number(line: &[u8]) -> usize {
+        let line = std::str::from_utf8(line).unwrap();
+        line.split_whitespace().next().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn replace_tabs_check() {
+        // do nothing on empty strings
+        let empty = b"";
+        assert!(empty.is_empty());
+        let empty = replace_tabs(empty);
+        assert!(empty.is_empty());
+
+        // Do nothing to strings with no tabs
+        let no_tabs = b"One string without tabs";
+        let replaced = replace_tabs(no_tabs);
+        assert_eq!(no_tabs.to_vec(), replaced);
+
+        // Replace tabs wit ^I
+        let with_tabs = b"One\tstring\twithout\ttabs";
+        let no_tabs = b"One^Istring^Iwithout^Itabs";
+        let replaced = replace_tabs(with_tabs);
+        assert_eq!(no_tabs.to_vec(), replaced);
+
+        // The other bytes are kept, even if they are not UTF-8
+        assert_eq!(b"\xff^I\xfe".to_vec(), replace_tabs(b"\xff\t\xfe"));
+    }
+
+    #[test]
+    fn unwrap_lines_() {
+        let unwrapped = unwrap_lines(b"");
+        assert_eq!(1, unwrapped.len());
+
+        let n: usize = 3;
+        let orig_lines = generate_test_string(n);
+        let unwrapped = unwrap_lines(orig_lines.as_bytes());
+        assert_eq!(n, unwrapped.len());
+
+        let n = 100;
+        let orig_lines = generate_test_string(n);
+        let unwrapped = unwrap_lines(orig_lines.as_bytes());
+        assert_eq!(n, unwrapped.len());
+    }
+
+    #[test]
+    fn append_line_number_check_returned_size() {
+        let mut orig_lines = vec![];
+        const N: usize = 100;
+        orig_lines = generate_test_vector(N);
+
+        let mod_lines = append_line_number(&orig_lines, false, 0_usize, 0);
+        assert_eq!(N, mod_lines.0.len());
+        assert_eq!(N, mod_lines.1);
+    }
+
+    #[test]
+    fn append_line_number_check_indexing() {
+        let mut orig_lines = vec![];
+        const N: usize = 100;
+        orig_lines = generate_test_vector(N);
+
+        let mod_lines = append_line_number(&orig_lines, false, 0_usize, 0);
+        for (i, line) in mod_lines.0.iter().enumerate() {
+            assert_eq!(i + 1, first_number(line))
+        }
+    }
+
+    #[test]
+    fn remove_consecutive_empty_lines_empty_input() {
+        let orig_lines = vec![];
+        let mod_lines =
+            remove_consecutive_empty_lines(&orig_lines, 0_usize, 1, SqueezeMode::Strict);
+        assert_eq!(0, mod_lines.0.len());
+        assert_eq!(0, mod_lines.1);
+
+        const N: usize = 100;
+
+        let mut orig_lines = vec![];
+        for _ in 0..N {
+            orig_lines.push(vec![]);
+        }
+
+        let mod_lines =
+            remove_consecutive_empty_lines(&orig_lines, 0_usize, 1, SqueezeMode::Strict);
+        assert_eq!(1, mod_lines.0.len()); // one line of the repeated chunk remains
+        assert_eq!(N, mod_lines.1);
+    }
+
+    #[test]
+    fn append_eol_character_check() {
+        let orig_lines = vec![];
+        let mod_lines = append_eol_character(&orig_lines);
+        assert_eq!(0, mod_lines.len());
+
+        const N: usize = 3;
+        let orig_lines = generate_test_vector(N);
+        let mod_lines = append_eol_character(&orig_lines);
+        assert_eq!(N, mod_lines.len());
+
+        for (i, line) in mod_lines.iter().enumerate() {
+            assert_eq!(format!("Line {}$", i + 1).into_bytes(), *line);
+        }
+    }
+
+    #[test]
+    fn remove_consecutive_empty_lines_check_count() {
+        const N: usize = 100;
+        let mut orig_lines = generate_test_vector(N);
+
+        for _ in 0..100 {
+            orig_lines.push(vec![]);
+        }
+
+        assert_eq!(N + 100, orig_lines.len());
+        let mod_lines =
+            remove_consecutive_empty_lines(&orig_lines, 0_usize, 1, SqueezeMode::Strict);
+        assert_eq!(N + 1, mod_lines.0.len());
+        assert_eq!(N, mod_lines.1);
+
+        // calling the function again should not remove more lines, but should
+        // report the emoty line at the end
+
+        let mod_lines =
+            remove_consecutive_empty_lines(&mod_lines.0, 0_usize, 1, SqueezeMode::Strict);
+        assert_eq!(N + 1, mod_lines.0.len());
+        assert_eq!(1, mod_lines.1);
+    }
+
+    #[test]
+    fn carriage_return_check() {
+        let orig_lines = lines(&["Line 1\r", "Line 2", "\r"]);
+        let stripped = strip_carriage_return(&orig_lines);
+        assert_eq!(lines(&["Line 1", "Line 2", ""]), stripped);
+
+        let appended = append_carriage_return(&stripped);
+        assert_eq!(lines(&["Line 1\r", "Line 2\r", "\r"]), appended);
+    }
+
+    #[test]
+    fn remove_consecutive_empty_lines_max_blank() {
+        let orig_lines = lines(&["Line 1", "", "", "", "Line 2"]);
+        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0, 2, SqueezeMode::Strict);
+        assert_eq!(lines(&["Line 1", "", "", "Line 2"]), mod_lines.0);
+
+        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0, 0, SqueezeMode::Strict);
+        assert_eq!(lines(&["Line 1", "Line 2"]), mod_lines.0);
+
+        // The counter carries over from the previous call
+        let mod_lines = remove_consecutive_empty_lines(&orig_lines[1..], 2, 2, SqueezeMode::Strict);
+        assert_eq!(lines(&["Line 2"]), mod_lines.0);
+    }
+
+    #[test]
+    fn remove_consecutive_whitespace_lines() {
+        let orig_lines = lines(&["Line 1", " \t", "\r", "", "Line 2"]);
+        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0, 1, SqueezeMode::Strict);
+        assert_eq!(lines(&["Line 1", " \t", "\r", "", "Line 2"]), mod_lines.0);
+
+        let mod_lines = remove_consecutive_empty_lines(&orig_lines, 0, 1, SqueezeMode::Whitespace);
+        assert_eq!(lines(&["Line 1", " \t", "Line 2"]), mod_lines.0);
+        assert_eq!(0, mod_lines.1);
+    }
+
+    #[test]
+    fn append_line_number_descending_check() {
+        const N: usize = 10;
+        let orig_lines = generate_test_vector(N);
+
+        let mod_lines = append_line_number_descending(&orig_lines, false, N + 1, 0);
+        assert_eq!(N, mod_lines.0.len());
+        assert_eq!(1, mod_lines.1);
+        for (i, line) in mod_lines.0.iter().enumerate() {
+            assert_eq!(N - i, first_number(line))
+        }
+
+        let orig_lines = lines(&["Line 2", "", "Line 1"]);
+        let mod_lines = append_line_number_descending(&orig_lines, true, 3, 0);
+        assert_eq!(lines(&["2 Line 2", "", "1 Line 1"]), mod_lines.0);
+        assert_eq!(1, mod_lines.1);
+
+        // A single line is aligned to the selected width
+        let mod_lines = append_line_number_descending(&orig_lines[..1], false, 10, 3);
+        assert_eq!(lines(&["  9 Line 2"]), mod_lines.0);
+    }
+}
@@ -0,0 +1,481 @@
+//! Reads the records of a file from the last one to the first one.
+//!
+//! The input is read backwards in blocks starting at the end, so the
+//! memory used is bounded by the block size and the longest record.
+
+use crate::pipeline::{Eol, OutputFlags, generate_output};
+use regex::bytes::Regex;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Size of the blocks read from the end of the input
+pub const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Separator found at the end of every record
+#[derive(Debug, Clone)]
+pub enum Separator {
+    Literal(Vec<u8>),
+    Regex(Regex),
+}
+
+impl Separator {
+    /// Returns the (start, end) positions of all the non empty
+    /// separators found in the data
+    fn find_all(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        match self {
+            Separator::Literal(sep) if sep.is_empty() => vec![],
+            Separator::Literal(sep) => {
+                let mut found = vec![];
+                let mut start = 0;
+                while start + sep.len() <= data.len() {
+                    if data[start..].starts_with(sep) {
+                        found.push((start, start + sep.len()));
+                        start += sep.len();
+                    } else {
+                        start += 1;
+                    }
+                }
+                found
+            }
+            Separator::Regex(re) => re
+                .find_iter(data)
+                .filter(|m| !m.is_empty())
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+        }
+    }
+
+    /// Splits a record into its contents and the separator at its end,
+    /// the separator is empty if the record is the last one of the input
+    /// and the input does not end with a separator
+    fn split<'a>(&self, record: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+        let sep_start = match self {
+            Separator::Literal(sep) if !sep.is_empty() && record.ends_with(sep) => {
+                Some(record.len() - sep.len())
+            }
+            Separator::Literal(_) => None,
+            Separator::Regex(_) => self
+                .find_all(record)
+                .into_iter()
+                .rev()
+                .find(|&(_, end)| end == record.len())
+                .map(|(start, _)| start),
+        };
+        record.split_at(sep_start.unwrap_or(record.len()))
+    }
+}
+
+/// Iterator over the records of the input in reverse order. Every item
+/// is a pair with the contents of the record and the separator that ends it.
+pub struct ReverseRecords<R> {
+    reader: R,
+    separator: Separator,
+    block_size: usize,
+    /// Number of bytes of the input before the carry that are still unread
+    pos: u64,
+    /// Start of the input that was read but not split in records yet, as
+    /// the blocks read in the input order
+    carry: VecDeque<Vec<u8>>,
+    /// Records ready to be returned, in the input order
+    records: Vec<Vec<u8>>,
+}
+
+impl<R: Read + Seek> ReverseRecords<R> {
+    pub fn new(reader: R, separator: Separator) -> io::Result<ReverseRecords<R>> {
+        ReverseRecords::with_block_size(reader, separator, BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(
+        mut reader: R,
+        separator: Separator,
+        block_size: usize,
+    ) -> io::Result<ReverseRecords<R>> {
+        let pos = reader.seek(SeekFrom::End(0))?;
+        Ok(ReverseRecords {
+            reader,
+            separator,
+            block_size: block_size.max(1),
+            pos,
+            carry: VecDeque::new(),
+            records: vec![],
+        })
+    }
+
+    /// Returns the reader, consuming the iterator
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the previous block of the input and splits it into records.
+    /// A separator at the start of the block may continue in the previous
+    /// block, so it is not used as a split point until more data is read.
+    fn read_block(&mut self) -> io::Result<()> {
+        let len = self.block_size.min(self.pos as usize);
+        self.pos -= len as u64;
+        self.reader.seek(SeekFrom::Start(self.pos))?;
+        let mut data = vec![0; len];
+        self.reader.read_exact(&mut data)?;
+
+        // The carry has no split points but the separator left at its start,
+        // so a literal separator is only searched in the block and the first
+        // bytes of the carry, enough for a separator starting in the block to
+        // move the one at the start of the carry. A regex match may be of any
+        // length, the whole data is searched.
+        let overlap = match &self.separator {
+            Separator::Literal(sep) => (2 * sep.len()).saturating_sub(1),
+            Separator::Regex(_) => usize::MAX,
+        };
+        let mut partial = false;
+        for chunk in self.carry.iter() {
+            let missing = len.saturating_add(overlap) - data.len();
+            if missing < chunk.len() {
+                data.extend_from_slice(&chunk[..missing]);
+                partial = true;
+                break;
+            }
+            data.extend_from_slice(chunk);
+        }
+
+        // A separator ending at the end of the data belongs to the last record
+        let at_start = self.pos == 0;
+        let ends: Vec<usize> = self
+            .separator
+            .find_all(&data)
+            .into_iter()
+            .filter(|&(start, end)| (partial || end < data.len()) && (start > 0 || at_start))
+            .map(|(_, end)| end)
+            .collect();
+
+        // The data is only joined with the carry when it is split
+        data.truncate(len);
+        if ends.is_empty() && !at_start {
+            self.carry.push_front(data);
+            return Ok(());
+        }
+        for chunk in self.carry.drain(..) {
+            data.extend_from_slice(&chunk);
+        }
+
+        let Some(&first) = ends.first() else {
+            self.records.push(data);
+            return Ok(());
+        };
+        if at_start {
+            self.records.push(data[..first].to_vec());
+        }
+        for window in ends.windows(2) {
+            self.records.push(data[window[0]..window[1]].to_vec());
+        }
+        self.records.push(data[ends[ends.len() - 1]..].to_vec());
+        data.truncate(if at_start { 0 } else { first });
+        if !data.is_empty() {
+            self.carry.push_back(data);
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Iterator for ReverseRecords<R> {
+    type Item = io::Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.records.pop() {
+                let (contents, separator) = self.separator.split(&record);
+                return Some(Ok((contents.to_vec(), separator.to_vec())));
+            }
+            if self.pos == 0 {
+                return None;
+            }
+            if let Err(e) = self.read_block() {
+                // Stop after the first error
+                self.pos = 0;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Formats the records of an input in reverse order with the same
+/// pipeline used by generate_output. Every item holds the formatted
+/// lines of one record, empty if it was squeezed, and the separator
+/// to print after them.
+pub struct ReversedOutput<R> {
+    records: ReverseRecords<R>,
+    output_flags: OutputFlags,
+    empty_line_counter: usize,
+    last_line_number: usize,
+    /// Last line number once all the records were formatted
+    end_line_number: Option<usize>,
+}
+
+impl<R: Read + Seek> ReversedOutput<R> {
+    /// Creates the output of one input, the counters are the ones left
+    /// by the previous input. When the lines are numbered the records are
+    /// counted first, so the numbers are aligned to the width of the record
+    /// count as in the forward order, and numbered with their original
+    /// position if number_descending is set.
+    pub fn new(
+        reader: R,
+        separator: Separator,
+        output_flags: &OutputFlags,
+        empty_line_counter: usize,
+        last_line_number: usize,
+    ) -> io::Result<ReversedOutput<R>> {
+        let mut output_flags = output_flags.clone();
+        let numbered = output_flags.numbers || output_flags.number_noblank;
+        let (reader, end_line_number) = if numbered {
+            let mut records = ReverseRecords::new(reader, separator.clone())?;
+            let mut count = 0;
+            let mut total = 0;
+            for record in records.by_ref() {
+                let (contents, _) = record?;
+                total += 1;
+                let contents = match output_flags.eol {
+                    Eol::Keep => &contents[..],
+                    _ => contents.strip_suffix(b"\r").unwrap_or(&contents),
+                };
+                if !output_flags.number_noblank || !contents.is_empty() {
+                    count += 1;
+                }
+            }
+            if output_flags.number_width == 0 {
+                output_flags.number_width = total.to_string().len();
+            }
+            let end_line_number = output_flags
+                .number_descending
+                .then_some(last_line_number + count);
+            (records.into_inner(), end_line_number)
+        } else {
+            (reader, None)
+        };
+
+        Ok(ReversedOutput {
+            records: ReverseRecords::new(reader, separator)?,
+            output_flags,
+            empty_line_counter,
+            last_line_number: end_line_number.map_or(last_line_number, |n| n + 1),
+            end_line_number,
+        })
+    }
+
+    /// Returns the blank line counter and the last line number to carry
+    /// to the next input
+    pub fn counters(&self) -> (usize, usize) {
+        (
+            self.empty_line_counter,
+            self.end_line_number.unwrap_or(self.last_line_number),
+        )
+    }
+}
+
+impl<R: Read + Seek> Iterator for ReversedOutput<R> {
+    type Item = io::Result<(Vec<Vec<u8>>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (contents, separator) = match self.records.next()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        // The last record may have no separator, a literal one is added
+        // so it is not joined with the next record
+        let separator = match &self.records.separator {
+            Separator::Literal(literal) if separator.is_empty() => literal.clone(),
+            _ => separator,
+        };
+
+        let output = generate_output(
+            &[contents],
+            &self.output_flags,
+            self.empty_line_counter,
+            self.last_line_number,
+        );
+        self.empty_line_counter = output.1;
+        self.last_line_number = output.2;
+
+        Some(Ok((output.0, separator)))
+    }
+}
+
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Opens a file to be read backwards, stdin is not seekable so it is
+/// read into memory
+pub fn open_seekable(fname: &str) -> io::Result<Box<dyn ReadSeek>> {
+    if fname == "-" {
+        let mut data = vec![];
+        io::stdin().lock().read_to_end(&mut data)?;
+        Ok(Box::new(io::Cursor::new(data)))
+    } else {
+        Ok(Box::new(fs::File::open(fname)?))
+    }
+}
+
+#[cfg(test)]
+mod reverse_tests {
+    use super::*;
+    use crate::pipeline::SqueezeMode;
+    use std::io::Cursor;
+
+    fn reverse(data: &str, separator: Separator, block_size: usize) -> Vec<(String, String)> {
+        ReverseRecords::with_block_size(Cursor::new(data.as_bytes()), separator, block_size)
+            .unwrap()
+            .map(|record| {
+                let (contents, separator) = record.unwrap();
+                (
+                    String::from_utf8(contents).unwrap(),
+                    String::from_utf8(separator).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn pairs(records: &[(&str, &str)]) -> Vec<(String, String)> {
+        records
+            .iter()
+            .map(|(c, s)| (c.to_string(), s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reverse_empty_input() {
+        let newline = Separator::Literal(b"\n".to_vec());
+        assert!(reverse("", newline, 4).is_empty());
+    }
+
+    #[test]
+    fn reverse_lines_any_block_size() {
+        let data = "Line 1\nLine 2\n\nLine 4";
+        let expected = pairs(&[
+            ("Line 4", ""),
+            ("", "\n"),
+            ("Line 2", "\n"),
+            ("Line 1", "\n"),
+        ]);
+        for block_size in 1..=data.len() + 1 {
+            let newline = Separator::Literal(b"\n".to_vec());
+            assert_eq!(expected, reverse(data, newline, block_size));
+        }
+
+        // No empty record is returned after the last separator
+        let newline = Separator::Literal(b"\n".to_vec());
+        let expected = pairs(&[("Line 2", "\n"), ("Line 1", "\n")]);
+        assert_eq!(expected, reverse("Line 1\nLine 2\n", newline, 3));
+    }
+
+    #[test]
+    fn reverse_long_record() {
+        // Every block of a record without separators is read once
+        let line = "x".repeat(1 << 20);
+        let data = format!("a\n{line}\nb");
+        let newline = Separator::Literal(b"\n".to_vec());
+        let expected = pairs(&[("b", ""), (&line, "\n"), ("a", "\n")]);
+        assert_eq!(expected, reverse(&data, newline, 16));
+    }
+
+    #[test]
+    fn reverse_literal_separator() {
+        let data = "a::b::::c::";
+        let expected = pairs(&[("c", "::"), ("", "::"), ("b", "::"), ("a", "::")]);
+        for block_size in 1..=data.len() + 1 {
+            let separator = Separator::Literal(b"::".to_vec());
+            assert_eq!(expected, reverse(data, separator, block_size));
+        }
+
+        // Runs of a separator that overlaps itself split the same way with
+        // any block size
+        let data = "x:::y::::::z:::::";
+        let separator = Separator::Literal(b":::".to_vec());
+        let expected = reverse(data, separator.clone(), data.len());
+        for block_size in 1..data.len() {
+            assert_eq!(expected, reverse(data, separator.clone(), block_size));
+        }
+    }
+
+    #[test]
+    fn reverse_regex_separator() {
+        let data = "a1b22c333d";
+        let expected = pairs(&[("d", ""), ("c", "333"), ("b", "22"), ("a", "1")]);
+        let separator = Separator::Regex(Regex::new("[0-9]+").unwrap());
+        for block_size in 1..=data.len() + 1 {
+            assert_eq!(expected, reverse(data, separator.clone(), block_size));
+        }
+    }
+
+    #[test]
+    fn reversed_output_numbers() {
+        let mut output_flags = OutputFlags {
+            numbers: true,
+            squeeze_blank: false,
+            squeeze_mode: SqueezeMode::Strict,
+            max_blank: 1,
+            number_noblank: false,
+            show_ends: false,
+            show_tabs: false,
+            eol: Eol::Keep,
+            number_descending: false,
+            number_width: 0,
+        };
+        let data = "Line 1\n\nLine 3\n";
+        let newline = Separator::Literal(b"\n".to_vec());
+
+        let mut output =
+            ReversedOutput::new(Cursor::new(data), newline.clone(), &output_flags, 0, 10).unwrap();
+        let lines: Vec<String> = output
+            .by_ref()
+            .map(|r| String::from_utf8(r.unwrap().0.concat()).unwrap())
+            .collect();
+        assert_eq!(vec!["11 Line 3", "12 ", "13 Line 1"], lines);
+        assert_eq!((0, 13), output.counters());
+
+        output_flags.number_descending = true;
+        let mut output =
+            ReversedOutput::new(Cursor::new(data), newline.clone(), &output_flags, 0, 10).unwrap();
+        let lines: Vec<String> = output
+            .by_ref()
+            .map(|r| String::from_utf8(r.unwrap().0.concat()).unwrap())
+            .collect();
+        assert_eq!(vec!["13 Line 3", "12 ", "11 Line 1"], lines);
+        assert_eq!((0, 13), output.counters());
+
+        output_flags.number_noblank = true;
+        let mut output =
+            ReversedOutput::new(Cursor::new(data), newline, &output_flags, 0, 10).unwrap();
+        let lines: Vec<String> = output
+            .by_ref()
+            .map(|r| String::from_utf8(r.unwrap().0.concat()).unwrap())
+            .collect();
+        assert_eq!(vec!["12 Line 3", "", "11 Line 1"], lines);
+        assert_eq!((0, 12), output.counters());
+
+        // The numbers are aligned to the width of the line count
+        let data = (1..=10).map(|i| format!("Line {i}\n")).collect::<String>();
+        output_flags.number_noblank = false;
+        output_flags.number_descending = false;
+        let newline = Separator::Literal(b"\n".to_vec());
+        let output = ReversedOutput::new(
+            Cursor::new(data.clone()),
+            newline.clone(),
+            &output_flags,
+            0,
+            0,
+        )
+        .unwrap();
+        let lines: Vec<String> = output
+            .map(|r| String::from_utf8(r.unwrap().0.concat()).unwrap())
+            .collect();
+        assert_eq!(" 1 Line 10", lines[0]);
+        assert_eq!("10 Line 1", lines[9]);
+
+        output_flags.number_descending = true;
+        let output = ReversedOutput::new(Cursor::new(data), newline, &output_flags, 0, 0).unwrap();
+        let lines: Vec<String> = output
+            .map(|r| String::from_utf8(r.unwrap().0.concat()).unwrap())
+            .collect();
+        assert_eq!("10 Line 10", lines[0]);
+        assert_eq!(" 1 Line 1", lines[9]);
+    }
+}